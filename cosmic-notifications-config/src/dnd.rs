@@ -0,0 +1,41 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+/// A recurring window of time in which Do Not Disturb is active.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct DndSchedule {
+    /// Days on which the window starts. Empty means every day.
+    pub days: Vec<Weekday>,
+    /// Start of the window in minutes since local midnight.
+    pub start: u16,
+    /// End of the window in minutes since local midnight. A value lower than
+    /// `start` ends the window on the following day.
+    pub end: u16,
+}
+
+/// Notifications which are still shown while Do Not Disturb is active.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct DndAllowList {
+    pub apps: Vec<String>,
+    pub categories: Vec<String>,
+    pub critical: bool,
+}
+
+impl Default for DndAllowList {
+    fn default() -> Self {
+        Self {
+            apps: Vec::new(),
+            categories: Vec::new(),
+            critical: true,
+        }
+    }
+}
@@ -1,5 +1,10 @@
 use cosmic_config::{CosmicConfigEntry, cosmic_config_derive::CosmicConfigEntry};
 
+mod dnd;
+pub use dnd::*;
+mod rules;
+pub use rules::*;
+
 pub const ID: &str = "com.system76.CosmicNotifications";
 
 pub const PANEL_NOTIFICATIONS_FD: &str = "PANEL_NOTIFICATIONS_FD";
@@ -22,6 +27,17 @@ pub enum Anchor {
 #[version = 1]
 pub struct NotificationsConfig {
     pub do_not_disturb: bool,
+    /// Do Not Disturb is active until this time, in seconds since the Unix epoch.
+    pub do_not_disturb_until: Option<u64>,
+    /// Recurring windows in which Do Not Disturb is active.
+    pub do_not_disturb_schedules: Vec<DndSchedule>,
+    /// Notifications which are shown even while Do Not Disturb is active.
+    pub do_not_disturb_allow: DndAllowList,
+    /// Activate Do Not Disturb while a fullscreen window is shown on the notifications output.
+    pub do_not_disturb_fullscreen: bool,
+    /// Activate Do Not Disturb while the screen is being shared or recorded through the desktop
+    /// portal.
+    pub do_not_disturb_screencast: bool,
     pub anchor: Anchor,
     /// The maximum number of notifications that can be displayed at once.
     pub max_notifications: u32,
@@ -33,18 +49,73 @@ pub struct NotificationsConfig {
     pub max_timeout_normal: Option<u32>,
     /// Max time in milliseconds a low priority notification can be displayed before being removed.
     pub max_timeout_low: Option<u32>,
+    /// Time in milliseconds a critical notification is displayed if the sender leaves it to the server.
+    /// Critical notifications do not expire if `None`.
+    pub default_timeout_urgent: Option<u32>,
+    /// Time in milliseconds a normal notification is displayed if the sender leaves it to the server.
+    pub default_timeout_normal: Option<u32>,
+    /// Time in milliseconds a low priority notification is displayed if the sender leaves it to the server.
+    pub default_timeout_low: Option<u32>,
+    /// Expire notifications which ask to never expire after the max or default timeout of their urgency.
+    pub expire_persistent: bool,
+    /// Time in milliseconds without input after which the user is considered away, and
+    /// notifications stop expiring until they are back. Disabled if `None`.
+    pub idle_threshold: Option<u32>,
+    /// The maximum number of notifications kept in the history.
+    pub history_max_count: u32,
+    /// Max time in seconds a notification is kept in the history.
+    pub history_max_age: Option<u64>,
+    /// The freedesktop sound theme used to look up notification sounds.
+    pub sound_theme: String,
+    /// Sound played for low priority notifications without a sound hint.
+    pub sound_low: Option<String>,
+    /// Sound played for normal notifications without a sound hint.
+    pub sound_normal: Option<String>,
+    /// Sound played for critical notifications without a sound hint.
+    pub sound_critical: Option<String>,
+    /// Rules applied to incoming notifications, in order.
+    pub rules: Vec<Rule>,
+    /// Send the signals about a notification to every client instead of only its sender,
+    /// for legacy clients which do not receive signals addressed to them.
+    pub broadcast_signals: bool,
+    /// The number of notifications a client can send at once for each application.
+    /// Rate limiting is disabled if 0.
+    pub rate_limit_burst: u32,
+    /// The number of notifications a client can send per minute for each application once
+    /// the burst is used up.
+    pub rate_limit_per_minute: u32,
 }
 
 impl Default for NotificationsConfig {
     fn default() -> Self {
         Self {
             do_not_disturb: false,
+            do_not_disturb_until: None,
+            do_not_disturb_schedules: Vec::new(),
+            do_not_disturb_allow: DndAllowList::default(),
+            do_not_disturb_fullscreen: false,
+            do_not_disturb_screencast: false,
             anchor: Anchor::default(),
             max_notifications: 3,
             max_per_app: 2,
             max_timeout_urgent: None,
             max_timeout_normal: Some(5000),
             max_timeout_low: Some(3000),
+            default_timeout_urgent: None,
+            default_timeout_normal: Some(5000),
+            default_timeout_low: Some(3000),
+            expire_persistent: false,
+            idle_threshold: Some(120_000),
+            history_max_count: 200,
+            history_max_age: Some(7 * 24 * 60 * 60),
+            sound_theme: String::from("freedesktop"),
+            sound_low: None,
+            sound_normal: None,
+            sound_critical: None,
+            rules: Vec::new(),
+            broadcast_signals: false,
+            rate_limit_burst: 20,
+            rate_limit_per_minute: 60,
         }
     }
 }
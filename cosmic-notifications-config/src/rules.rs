@@ -0,0 +1,40 @@
+use std::path::PathBuf;
+
+/// A rule applied to incoming notifications which match all of its conditions.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct Rule {
+    pub matches: RuleMatch,
+    pub actions: Vec<RuleAction>,
+}
+
+/// Conditions of a [`Rule`]. Conditions which are `None` match any notification.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct RuleMatch {
+    pub app_name: Option<String>,
+    /// The `desktop-entry` hint.
+    pub desktop_entry: Option<String>,
+    /// The executable of the process which sent the notification.
+    pub executable: Option<PathBuf>,
+    /// Matches the category exactly, or any category of its class (`im` matches `im.received`).
+    pub category: Option<String>,
+    pub urgency: Option<u8>,
+    /// Regular expression matched against the summary.
+    pub summary: Option<String>,
+    /// Regular expression matched against the body.
+    pub body: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum RuleAction {
+    /// Discard the notification.
+    Drop,
+    /// Don't show a popup, but keep the notification in the history.
+    Mute,
+    /// Replace the urgency of the notification.
+    Urgency(u8),
+    /// Replace the expire timeout of the notification, in milliseconds.
+    Timeout(i32),
+    SuppressSound,
+    Transient,
+}
@@ -1,6 +1,107 @@
+use crate::{BodyImage, Image, markup};
 use fast_image_resize as fr;
-use std::str::FromStr;
+use rustix::fs::{Mode, OFlags};
+use std::{fs::File, io::BufReader, path::Path, str::FromStr};
 use zbus::zvariant::{Signature, Structure};
+
+/// Largest width or height of an image shown inline in a notification body.
+pub const BODY_IMAGE_SIZE: u32 = 256;
+/// Image files larger than this are not loaded.
+const BODY_IMAGE_MAX_FILE_SIZE: u64 = 16 * 1024 * 1024;
+/// Images wider or higher than this are not decoded.
+const BODY_IMAGE_MAX_DIMENSION: u32 = 4096;
+/// The most memory decoding a body image may allocate, since a small file can
+/// expand to a much larger image.
+const BODY_IMAGE_MAX_ALLOC: u64 = 64 * 1024 * 1024;
+/// Images after this many in a body are shown by their alternative text.
+pub const MAX_BODY_IMAGES: usize = 4;
+
+/// Decodes an image file for display in a notification body, downscaling it to
+/// fit within [`BODY_IMAGE_SIZE`].
+///
+/// Only regular files are read. The file is opened without blocking, so a FIFO
+/// or device can't stall the caller before it is rejected.
+pub fn load_body_image(path: &Path) -> Option<Image> {
+    let file = match rustix::fs::open(
+        path,
+        OFlags::RDONLY | OFlags::NONBLOCK | OFlags::CLOEXEC,
+        Mode::empty(),
+    ) {
+        Ok(fd) => File::from(fd),
+        Err(err) => {
+            tracing::warn!("Failed to open body image {}: {}", path.display(), err);
+            return None;
+        }
+    };
+    match file.metadata() {
+        Ok(metadata) if !metadata.is_file() => {
+            tracing::warn!("Body image {} is not a regular file", path.display());
+            return None;
+        }
+        Ok(metadata) if metadata.len() > BODY_IMAGE_MAX_FILE_SIZE => {
+            tracing::warn!("Body image {} is too large", path.display());
+            return None;
+        }
+        Ok(_) => {}
+        Err(err) => {
+            tracing::warn!("Failed to read body image {}: {}", path.display(), err);
+            return None;
+        }
+    }
+
+    let mut reader = ::image::ImageReader::new(BufReader::new(file));
+    if let Ok(format) = ::image::ImageFormat::from_path(path) {
+        reader.set_format(format);
+    }
+    let mut limits = ::image::Limits::default();
+    limits.max_image_width = Some(BODY_IMAGE_MAX_DIMENSION);
+    limits.max_image_height = Some(BODY_IMAGE_MAX_DIMENSION);
+    limits.max_alloc = Some(BODY_IMAGE_MAX_ALLOC);
+    let image = match reader
+        .with_guessed_format()
+        .map_err(::image::ImageError::IoError)
+        .and_then(|mut reader| {
+            reader.limits(limits);
+            reader.decode()
+        }) {
+        Ok(image) => image,
+        Err(err) => {
+            tracing::warn!("Failed to decode body image {}: {}", path.display(), err);
+            return None;
+        }
+    };
+
+    let image = if image.width() > BODY_IMAGE_SIZE || image.height() > BODY_IMAGE_SIZE {
+        image.thumbnail(BODY_IMAGE_SIZE, BODY_IMAGE_SIZE)
+    } else {
+        image
+    }
+    .into_rgba8();
+
+    Some(Image::Data {
+        width: image.width(),
+        height: image.height(),
+        data: image.into_raw(),
+    })
+}
+
+/// Loads the images referenced by `<img>` tags in `body`, up to
+/// [`MAX_BODY_IMAGES`]. Decoding blocks, so this should not run on an async task.
+pub fn load_body_images(body: &str) -> Vec<BodyImage> {
+    markup::html_to_images(body)
+        .into_iter()
+        .enumerate()
+        .map(|(i, img)| BodyImage {
+            image: img
+                .src
+                .as_deref()
+                .filter(|_| i < MAX_BODY_IMAGES)
+                .and_then(load_body_image),
+            alt: img.alt,
+        })
+        .collect()
+}
+
 pub struct ImageData {
     pub width: u32,
     pub height: u32,
@@ -11,6 +112,18 @@ pub struct ImageData {
     pub data: Vec<u8>,
 }
 
+/// Writes RGBA pixel data to `path` as a PNG.
+pub fn write_png(path: &Path, width: u32, height: u32, data: &[u8]) -> ::image::ImageResult<()> {
+    ::image::save_buffer_with_format(
+        path,
+        data,
+        width,
+        height,
+        ::image::ExtendedColorType::Rgba8,
+        ::image::ImageFormat::Png,
+    )
+}
+
 impl ImageData {
     pub fn into_rgba(self) -> Self {
         let rgba = if self.has_alpha {
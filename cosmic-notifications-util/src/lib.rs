@@ -4,6 +4,7 @@ pub mod image;
 pub use image::*;
 
 pub mod markup;
+pub mod store;
 
 use cosmic::widget::{Icon, icon};
 use serde::{Deserialize, Serialize};
@@ -20,8 +21,25 @@ pub struct Notification {
     pub body: String,
     pub actions: Vec<(ActionId, String)>,
     pub hints: Vec<Hint>,
+    /// Images referenced by `<img>` tags in the body, in order of appearance.
+    /// They are not loaded by [`Notification::new`], see `load_body_images`.
+    #[serde(default)]
+    pub body_images: Vec<BodyImage>,
     pub expire_timeout: i32,
     pub time: SystemTime,
+    /// What the server verified about the client which sent the notification.
+    #[serde(default)]
+    pub provenance: Option<Provenance>,
+    /// The name and icon from the desktop file of the application, shown
+    /// instead of the reported `app_name` and `app_icon`.
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub display_icon: Option<String>,
+    /// The icons of the actions found in the icon theme, if the
+    /// "action-icons" hint is set. See [`Notification::resolve_action_icons`].
+    #[serde(default)]
+    pub action_icon_paths: Vec<(ActionId, PathBuf)>,
 }
 
 impl Notification {
@@ -56,8 +74,14 @@ impl Notification {
                 "suppress-sound" => bool::try_from(v).map(Hint::SuppressSound).ok(),
                 "transient" => bool::try_from(v).map(Hint::Transient).ok(),
                 "x" => i32::try_from(v).map(Hint::X).ok(),
+                "x-kde-reply-placeholder-text" => {
+                    String::try_from(v).map(Hint::ReplyPlaceholder).ok()
+                }
                 "y" => i32::try_from(v).map(Hint::Y).ok(),
                 "urgency" => u8::try_from(v).map(Hint::Urgency).ok(),
+                "value" => i32::try_from(v)
+                    .map(|v| Hint::Value(v.clamp(0, 100) as u8))
+                    .ok(),
                 "image-path" | "image_path" => String::try_from(v).ok().map(|s| {
                     Hint::Image(
                         url::Url::parse(&s)
@@ -101,8 +125,13 @@ impl Notification {
             body: body.to_string(),
             actions,
             hints,
+            body_images: Vec::new(),
             expire_timeout,
             time: SystemTime::now(),
+            provenance: None,
+            display_name: None,
+            display_icon: None,
+            action_icon_paths: Vec::new(),
         }
     }
 
@@ -110,6 +139,37 @@ impl Notification {
         self.hints.contains(&Hint::Transient(true))
     }
 
+    pub fn action_icons(&self) -> bool {
+        self.hints.contains(&Hint::ActionIcons(true))
+    }
+
+    /// Looks up the icons named by the action keys once, instead of every time
+    /// the actions are shown.
+    pub fn resolve_action_icons(&mut self) {
+        if !self.action_icons() {
+            return;
+        }
+        self.action_icon_paths = self
+            .actions
+            .iter()
+            .filter_map(|(action, _)| {
+                let name = action.to_string();
+                let path = icon::from_name(name.as_str())
+                    .size(16)
+                    .symbolic(name.ends_with("-symbolic"))
+                    .path()?;
+                Some((action.clone(), path))
+            })
+            .collect();
+    }
+
+    /// The icon of `action`, if it was found by [`Notification::resolve_action_icons`].
+    pub fn action_icon(&self, action: &ActionId) -> Option<&PathBuf> {
+        self.action_icon_paths
+            .iter()
+            .find_map(|(a, path)| (a == action).then_some(path))
+    }
+
     pub fn category(&self) -> Option<&str> {
         self.hints.iter().find_map(|h| match h {
             Hint::Category(s) => Some(s.as_str()),
@@ -117,6 +177,40 @@ impl Notification {
         })
     }
 
+    /// Whether the notification has `category`, or a category of the class `category`.
+    pub fn category_matches(&self, category: &str) -> bool {
+        self.category().is_some_and(|c| {
+            c == category
+                || c.strip_prefix(category)
+                    .is_some_and(|rest| rest.starts_with('.'))
+        })
+    }
+
+    /// The label of the inline reply action, if the notification can be replied to.
+    pub fn inline_reply(&self) -> Option<&str> {
+        self.actions
+            .iter()
+            .find_map(|(action, label)| match action {
+                ActionId::Custom(key) if key == INLINE_REPLY => Some(label.as_str()),
+                _ => None,
+            })
+    }
+
+    /// The progress in percent.
+    pub fn value(&self) -> Option<u8> {
+        self.hints.iter().find_map(|h| match h {
+            Hint::Value(v) => Some(*v),
+            _ => None,
+        })
+    }
+
+    pub fn reply_placeholder(&self) -> Option<&str> {
+        self.hints.iter().find_map(|h| match h {
+            Hint::ReplyPlaceholder(s) => Some(s.as_str()),
+            _ => None,
+        })
+    }
+
     pub fn desktop_entry(&self) -> Option<&str> {
         self.hints.iter().find_map(|h| match h {
             Hint::DesktopEntry(s) => Some(s.as_str()),
@@ -134,6 +228,16 @@ impl Notification {
             .unwrap_or(1)
     }
 
+    /// The name of the application to show, from its desktop file if known.
+    pub fn display_name(&self) -> &str {
+        self.display_name.as_deref().unwrap_or(&self.app_name)
+    }
+
+    /// The icon of the application to show, from its desktop file if known.
+    pub fn display_icon(&self) -> &str {
+        self.display_icon.as_deref().unwrap_or(&self.app_icon)
+    }
+
     pub fn image(&self) -> Option<&Image> {
         self.hints.iter().find_map(|h| match h {
             Hint::Image(i) => Some(i),
@@ -151,16 +255,21 @@ impl Notification {
                 data,
             }) => Some(icon::from_raster_pixels(*width, *height, data.clone()).icon()),
             None => {
-                if !self.app_icon.is_empty() {
+                let app_icon = self.display_icon();
+                if !app_icon.is_empty() {
+                    // Desktop files may name their icon with an absolute path
+                    if app_icon.starts_with('/') {
+                        return Some(icon::from_path(PathBuf::from(app_icon)).icon());
+                    }
                     // Handle file:// URLs in app_icon
-                    if self.app_icon.starts_with("file://")
-                        && let Ok(url) = url::Url::parse(&self.app_icon)
+                    if app_icon.starts_with("file://")
+                        && let Ok(url) = url::Url::parse(app_icon)
                         && let Ok(path) = url.to_file_path()
                     {
                         return Some(icon::from_path(path).icon());
                     }
                     // Otherwise treat as icon name
-                    Some(icon::from_name(self.app_icon.as_str()).icon())
+                    Some(icon::from_name(app_icon).icon())
                 } else {
                     None
                 }
@@ -173,6 +282,10 @@ impl Notification {
     }
 }
 
+/// The key of the action which is replaced by a text field to reply inline,
+/// as used by KDE.
+pub const INLINE_REPLY: &str = "inline-reply";
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ActionId {
     Default,
@@ -214,6 +327,10 @@ pub enum Hint {
     Urgency(u8),
     X(i32),
     Y(i32),
+    /// Text shown in the inline reply field while it is empty.
+    ReplyPlaceholder(String),
+    /// Progress in percent, from 0 to 100.
+    Value(u8),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -229,6 +346,65 @@ pub enum Image {
     },
 }
 
+/// The process which sent a notification, as found by the server instead of
+/// reported by the client.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Provenance {
+    pub pid: Option<u32>,
+    pub uid: Option<u32>,
+    pub exe: Option<PathBuf>,
+    /// The cgroup of the process, which names the systemd unit or scope it runs in.
+    pub cgroup: Option<String>,
+    /// The application id, if the process runs in a Flatpak sandbox.
+    pub flatpak_id: Option<String>,
+    /// The AppArmor label of the process, such as `snap.<snap>.<app> (enforce)`.
+    pub apparmor_label: Option<String>,
+}
+
+impl Provenance {
+    /// The id of the application if the process runs in a Flatpak or snap
+    /// sandbox, which is also the name of its desktop file.
+    pub fn sandboxed_app_id(&self) -> Option<String> {
+        self.flatpak_id.clone().or_else(|| {
+            // Snap applications run in units named `snap.<snap>.<app>`, followed
+            // by a unique suffix for scopes. Any process can name its own unit,
+            // so the snap must also be confirmed by its AppArmor profile.
+            let unit = self.cgroup.as_deref()?.rsplit('/').next()?;
+            let unit = unit
+                .strip_suffix(".scope")
+                .or_else(|| unit.strip_suffix(".service"))?;
+            let (snap, app) = unit.strip_prefix("snap.")?.split_once('.')?;
+            let (confined, _) = self
+                .apparmor_label
+                .as_deref()?
+                .strip_prefix("snap.")?
+                .split_once('.')?;
+            if confined != snap {
+                return None;
+            }
+            let app = app.split('.').next()?;
+            let app = match app.len().checked_sub(37) {
+                Some(at) if app.get(at..).is_some_and(is_uuid_suffix) => &app[..at],
+                _ => app,
+            };
+            (!snap.is_empty() && !app.is_empty()).then(|| format!("{snap}_{app}"))
+        })
+    }
+}
+
+/// Whether `s` is a `-` followed by a UUID.
+fn is_uuid_suffix(s: &str) -> bool {
+    s.len() == 37 && s.starts_with('-') && s[1..].chars().all(|c| c.is_ascii_hexdigit() || c == '-')
+}
+
+/// An inline body image, or its alternative text if it could not be loaded.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BodyImage {
+    pub image: Option<Image>,
+    pub alt: String,
+}
+
 #[repr(u32)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum CloseReason {
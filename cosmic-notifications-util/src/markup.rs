@@ -6,6 +6,35 @@ use cosmic::{
         font::{Style, Weight},
     },
 };
+use std::{os::unix::fs::PermissionsExt, path::PathBuf};
+use url::Url;
+
+/// URL schemes which may be opened from a notification body.
+const LINK_SCHEMES: &[&str] = &["http", "https", "mailto", "file"];
+
+/// Parses the `href` of an `<a>` tag, rejecting relative links, schemes
+/// that are not allowed and files which would be run when opened.
+fn parse_link(href: &str) -> Option<Url> {
+    let url = Url::parse(href.trim()).ok()?;
+    (LINK_SCHEMES.contains(&url.scheme()) && !file_link_launches(&url)).then_some(url)
+}
+
+/// Whether `url` is a `file:` link which `xdg-open` would run instead of
+/// show: a desktop entry, an executable file or a file on another host.
+pub fn file_link_launches(url: &Url) -> bool {
+    if url.scheme() != "file" {
+        return false;
+    }
+    let Ok(path) = url.to_file_path() else {
+        return true;
+    };
+    let path = path.canonicalize().unwrap_or(path);
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("desktop"))
+        || path
+            .metadata()
+            .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
 
 // Handle break lines, etc. in the future
 // Used only in `parse_html` function
@@ -21,12 +50,12 @@ fn _prepare_html(text: &str) -> String {
 
 // Sanitize only tags allowed by Freedesktop Notification Specifications
 // https://specifications.freedesktop.org/notification/1.2/markup.html
-// TODO: impl <img> tag handling
-fn sanitize_html(tags: &[String], content: &str) -> Span<'static> {
+// `<img>` tags are marked by `_handle_recursive` and loaded from `html_to_images`
+fn sanitize_html(tags: &[(String, Option<Url>)], content: &str) -> Span<'static, Url> {
     let mut font = Font::default();
     let mut span = Span::new(content.to_owned());
 
-    for tag in tags {
+    for (tag, link) in tags {
         match tag.as_str() {
             "b" => font.weight = Weight::Bold,
             "i" => font.style = Style::Italic,
@@ -34,6 +63,9 @@ fn sanitize_html(tags: &[String], content: &str) -> Span<'static> {
             "a" => {
                 let theme = cosmic_theme::Theme::preferred_theme();
                 span = span.underline(true).color(theme.accent_text_color());
+                if let Some(link) = link {
+                    span = span.link(link.clone());
+                }
             }
             _ => {}
         }
@@ -42,17 +74,41 @@ fn sanitize_html(tags: &[String], content: &str) -> Span<'static> {
     span.font(font)
 }
 
+/// A part of a notification body, which is either text or an image.
+#[derive(Debug, Clone)]
+pub enum BodyBlock {
+    Text(Vec<Span<'static, Url>>),
+    /// The image of the nth `<img>` tag, as returned by [`html_to_images`].
+    Image(usize),
+}
+
 fn _handle_recursive(
     handle: &tl::NodeHandle,
     parser: &tl::Parser,
-    tags: &mut Vec<String>,
-    buffer: &mut Vec<Span<'static>>,
+    tags: &mut Vec<(String, Option<Url>)>,
+    buffer: &mut Vec<BodyBlock>,
 ) {
     if let Some(node) = handle.get(parser) {
         match node {
             tl::Node::Tag(tag) => {
                 let tag_name = tag.name().as_utf8_str();
-                tags.push(tag_name.into_owned());
+                if tag_name == "img" {
+                    let nth = buffer
+                        .iter()
+                        .filter(|block| matches!(block, BodyBlock::Image(_)))
+                        .count();
+                    buffer.push(BodyBlock::Image(nth));
+                    return;
+                }
+                let link = if tag_name == "a" {
+                    tag.attributes()
+                        .get("href")
+                        .flatten()
+                        .and_then(|href| parse_link(&href.as_utf8_str()))
+                } else {
+                    None
+                };
+                tags.push((tag_name.into_owned(), link));
 
                 tag.children().top().iter().for_each(|t| {
                     _handle_recursive(t, parser, tags, buffer);
@@ -61,14 +117,33 @@ fn _handle_recursive(
                 tags.pop();
             }
             tl::Node::Raw(bytes) => {
-                buffer.push(sanitize_html(tags, &bytes.as_utf8_str()));
+                let span = sanitize_html(tags, &bytes.as_utf8_str());
+                match buffer.last_mut() {
+                    Some(BodyBlock::Text(spans)) => spans.push(span),
+                    _ => buffer.push(BodyBlock::Text(vec![span])),
+                }
             }
             _ => {}
         }
     }
 }
 
-pub fn html_to_spans(text: &str) -> Vec<Span<'static>> {
+/// Converts notification body markup into styled spans.
+///
+/// Spans inside `<a href="...">` carry their link when it uses an allowed scheme.
+pub fn html_to_spans(text: &str) -> Vec<Span<'static, Url>> {
+    html_to_blocks(text)
+        .into_iter()
+        .flat_map(|block| match block {
+            BodyBlock::Text(spans) => spans,
+            BodyBlock::Image(_) => Vec::new(),
+        })
+        .collect()
+}
+
+/// Converts notification body markup into styled text, split where `<img>`
+/// tags appear.
+pub fn html_to_blocks(text: &str) -> Vec<BodyBlock> {
     let mut buffer = Vec::new();
     let html = _prepare_html(text);
     let dom = tl::parse(&html, tl::ParserOptions::default());
@@ -85,3 +160,47 @@ pub fn html_to_spans(text: &str) -> Vec<Span<'static>> {
 
     buffer
 }
+
+/// An `<img>` tag found in a notification body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImgTag {
+    /// Local path of the image, if `src` is an absolute path or a `file://` URL.
+    pub src: Option<PathBuf>,
+    pub alt: String,
+}
+
+pub fn html_to_images(text: &str) -> Vec<ImgTag> {
+    let Ok(vdom) = tl::parse(text, tl::ParserOptions::default()) else {
+        return Vec::new();
+    };
+    let parser = vdom.parser();
+    let Some(images) = vdom.query_selector("img") else {
+        return Vec::new();
+    };
+
+    images
+        .filter_map(|handle| handle.get(parser)?.as_tag())
+        .map(|tag| {
+            let attr = |name: &str| {
+                tag.attributes()
+                    .get(name)
+                    .flatten()
+                    .map(|v| v.as_utf8_str().into_owned())
+            };
+            let src = attr("src").and_then(|src| {
+                if src.starts_with('/') {
+                    Some(PathBuf::from(src))
+                } else {
+                    Url::parse(&src)
+                        .ok()
+                        .filter(|url| url.scheme() == "file")
+                        .and_then(|url| url.to_file_path().ok())
+                }
+            });
+            ImgTag {
+                src,
+                alt: attr("alt").unwrap_or_default(),
+            }
+        })
+        .collect()
+}
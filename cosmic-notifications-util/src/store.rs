@@ -0,0 +1,247 @@
+//! The displayed notifications and the history, independent of how they are drawn.
+
+use crate::Notification;
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, VecDeque},
+};
+
+/// A change of the displayed notifications which the UI has to carry out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    /// Notifications are displayed while none were before.
+    Show,
+    /// No notification is displayed anymore.
+    Hide,
+    /// The displayed cards changed, and the first `visible` of them should be laid out.
+    Reposition { visible: usize },
+    /// The notification was added to the history.
+    Archived(u32),
+    /// A notification was removed from the history.
+    HistoryChanged,
+}
+
+#[derive(Debug, Clone)]
+pub struct NotificationStore {
+    /// Displayed notifications, the most important last.
+    active: Vec<Notification>,
+    /// Notifications which are no longer displayed, the most recent first.
+    hidden: VecDeque<Notification>,
+    max_visible: usize,
+    max_per_app: u32,
+}
+
+impl NotificationStore {
+    pub fn new(hidden: VecDeque<Notification>, max_visible: u32, max_per_app: u32) -> Self {
+        Self {
+            active: Vec::with_capacity(50),
+            hidden,
+            max_visible: max_visible as usize,
+            max_per_app,
+        }
+    }
+
+    pub fn active(&self) -> &[Notification] {
+        &self.active
+    }
+
+    pub fn hidden(&self) -> &VecDeque<Notification> {
+        &self.hidden
+    }
+
+    pub fn hidden_mut(&mut self) -> &mut VecDeque<Notification> {
+        &mut self.hidden
+    }
+
+    /// Finds a notification, whether it is displayed or in the history.
+    pub fn get(&self, id: u32) -> Option<&Notification> {
+        self.active
+            .iter()
+            .chain(self.hidden.iter())
+            .find(|n| n.id == id)
+    }
+
+    /// Finds a notification to change what is shown of it, without affecting
+    /// its position.
+    pub fn get_mut(&mut self, id: u32) -> Option<&mut Notification> {
+        self.active
+            .iter_mut()
+            .chain(self.hidden.iter_mut())
+            .find(|n| n.id == id)
+    }
+
+    pub fn is_active(&self, id: u32) -> bool {
+        self.active.iter().any(|n| n.id == id)
+    }
+
+    /// The number of cards which fit on screen.
+    pub fn visible(&self) -> usize {
+        self.active.len().min(self.max_visible)
+    }
+
+    /// The `nth` card, counted from the most important one.
+    pub fn card(&self, nth: usize) -> Option<&Notification> {
+        self.active.iter().rev().nth(nth)
+    }
+
+    pub fn set_limits(&mut self, max_visible: u32, max_per_app: u32) -> Vec<Effect> {
+        if self.max_visible == max_visible as usize && self.max_per_app == max_per_app {
+            return Vec::new();
+        }
+
+        self.max_visible = max_visible as usize;
+        self.max_per_app = max_per_app;
+        if self.active.is_empty() {
+            return Vec::new();
+        }
+        self.sort();
+        self.group();
+        vec![Effect::Reposition {
+            visible: self.visible(),
+        }]
+    }
+
+    /// Displays a notification, taking it out of the history if it was there.
+    pub fn push(&mut self, notification: Notification) -> Vec<Effect> {
+        let mut effects = Vec::with_capacity(2);
+        if let Some(pos) = self.hidden.iter().position(|n| n.id == notification.id) {
+            self.hidden.remove(pos);
+            effects.push(Effect::HistoryChanged);
+        }
+        if self.active.is_empty() {
+            effects.push(Effect::Show);
+        }
+
+        self.sort();
+        match self.active.binary_search_by(|a| priority(a, &notification)) {
+            Ok(pos) => self.active[pos] = notification,
+            Err(pos) => self.active.insert(pos, notification),
+        }
+        self.group();
+        effects.push(Effect::Reposition {
+            visible: self.visible(),
+        });
+        effects
+    }
+
+    /// Updates a displayed notification in place, keeping its card unless its
+    /// urgency changed. A notification which is not displayed, because it
+    /// expired or is unknown, is displayed again.
+    pub fn replace(&mut self, mut notification: Notification) -> Vec<Effect> {
+        let Some(n) = self.active.iter_mut().find(|n| n.id == notification.id) else {
+            return self.push(notification);
+        };
+
+        // Cards are ordered by the time they were first displayed.
+        notification.time = n.time;
+        let reorder = n.urgency() != notification.urgency();
+        *n = notification;
+        if !reorder {
+            return Vec::new();
+        }
+        self.sort();
+        self.group();
+        vec![Effect::Reposition {
+            visible: self.visible(),
+        }]
+    }
+
+    /// Moves a displayed notification to the history.
+    pub fn expire(&mut self, id: u32) -> Vec<Effect> {
+        let Some(pos) = self.active.iter().position(|n| n.id == id) else {
+            return Vec::new();
+        };
+
+        let notification = self.active.remove(pos);
+        let mut effects = vec![self.removed()];
+        self.hidden.push_front(notification);
+        effects.push(Effect::Archived(id));
+        effects
+    }
+
+    /// Adds a notification to the history without displaying it, hiding the
+    /// card it replaces if there is one.
+    pub fn archive(&mut self, notification: Notification) -> Vec<Effect> {
+        let id = notification.id;
+        let mut effects = Vec::with_capacity(2);
+        if let Some(pos) = self.active.iter().position(|n| n.id == id) {
+            self.active.remove(pos);
+            effects.push(self.removed());
+        }
+        self.hidden.retain(|h| h.id != id);
+        self.hidden.push_front(notification);
+        effects.push(Effect::Archived(id));
+        effects
+    }
+
+    /// Removes a notification, whether it is displayed or in the history.
+    ///
+    /// Returns `None` if there is no notification with this id.
+    pub fn close(&mut self, id: u32) -> Option<Vec<Effect>> {
+        if let Some(pos) = self.active.iter().position(|n| n.id == id) {
+            self.active.remove(pos);
+            if self.active.is_empty() {
+                self.active.shrink_to(50);
+            }
+            return Some(vec![self.removed()]);
+        }
+
+        let pos = self.hidden.iter().position(|n| n.id == id)?;
+        self.hidden.remove(pos);
+        Some(vec![Effect::HistoryChanged])
+    }
+
+    /// Empties the history, returning the ids of the removed notifications.
+    pub fn clear_history(&mut self) -> Vec<u32> {
+        self.hidden.drain(..).map(|n| n.id).collect()
+    }
+
+    fn removed(&mut self) -> Effect {
+        if self.active.is_empty() {
+            return Effect::Hide;
+        }
+        self.sort();
+        self.group();
+        Effect::Reposition {
+            visible: self.visible(),
+        }
+    }
+
+    fn sort(&mut self) {
+        self.active.sort_by(priority);
+    }
+
+    /// Makes room for other apps when not every card fits on screen, by
+    /// moving the older cards of apps exceeding `max_per_app` behind all
+    /// others. Critical notifications are never moved.
+    fn group(&mut self) {
+        if self.max_per_app == 0 || self.active.len() <= self.max_visible {
+            return;
+        }
+
+        let mut per_app: HashMap<String, u32> = HashMap::new();
+        let mut kept = Vec::with_capacity(self.active.len());
+        let mut extra = Vec::new();
+        for n in self.active.drain(..).rev() {
+            let count = per_app.entry(n.display_name().to_string()).or_default();
+            *count += 1;
+            if *count > self.max_per_app && n.urgency() < 2 {
+                extra.push(n);
+            } else {
+                kept.push(n);
+            }
+        }
+        kept.extend(extra);
+        kept.reverse();
+        self.active = kept;
+    }
+}
+
+/// Orders notifications by urgency, then by time. Ties are broken by id, so
+/// that only the same notification compares equal.
+fn priority(a: &Notification, b: &Notification) -> Ordering {
+    a.urgency()
+        .cmp(&b.urgency())
+        .then_with(|| a.time.cmp(&b.time))
+        .then_with(|| a.id.cmp(&b.id))
+}
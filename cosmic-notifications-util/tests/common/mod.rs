@@ -0,0 +1,14 @@
+//! Helpers shared by the tests of both crates.
+
+use std::path::PathBuf;
+
+/// An empty directory for the files of one test.
+pub fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "cosmic-notifications-{}-{name}",
+        std::process::id()
+    ));
+    _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
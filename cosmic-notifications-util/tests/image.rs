@@ -0,0 +1,58 @@
+mod common;
+
+use common::test_dir;
+use cosmic_notifications_util::{Image, load_body_image, write_png};
+use rustix::fs::{CWD, FileType, Mode, mknodat};
+
+#[test]
+fn fifo_is_rejected_without_blocking() {
+    let dir = test_dir("fifo");
+    let path = dir.join("image.png");
+    mknodat(CWD, &path, FileType::Fifo, Mode::RUSR | Mode::WUSR, 0).unwrap();
+
+    assert_eq!(load_body_image(&path), None);
+    _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn directory_is_rejected() {
+    let dir = test_dir("directory");
+
+    assert_eq!(load_body_image(&dir), None);
+    _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn missing_file_is_rejected() {
+    let dir = test_dir("missing");
+
+    assert_eq!(load_body_image(&dir.join("image.png")), None);
+    _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn image_is_downscaled() {
+    let dir = test_dir("downscaled");
+    let path = dir.join("image.png");
+    write_png(&path, 512, 2, &[255; 512 * 2 * 4]).unwrap();
+
+    assert!(matches!(
+        load_body_image(&path),
+        Some(Image::Data {
+            width: 256,
+            height: 1,
+            ..
+        })
+    ));
+    _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn oversized_image_is_rejected() {
+    let dir = test_dir("oversized");
+    let path = dir.join("image.png");
+    write_png(&path, 5000, 1, &[255; 5000 * 4]).unwrap();
+
+    assert_eq!(load_body_image(&path), None);
+    _ = std::fs::remove_dir_all(&dir);
+}
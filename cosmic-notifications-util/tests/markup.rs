@@ -0,0 +1,150 @@
+use std::path::{Path, PathBuf};
+
+use cosmic_notifications_util::markup::{
+    BodyBlock, ImgTag, html_to_blocks, html_to_images, html_to_spans,
+};
+use url::Url;
+
+fn links(body: &str) -> Vec<Option<Url>> {
+    html_to_spans(body)
+        .into_iter()
+        .map(|span| span.link)
+        .collect()
+}
+
+fn img_src(src: &str) -> Option<PathBuf> {
+    let images = html_to_images(&format!(r#"<img src="{src}" alt="image">"#));
+    assert_eq!(images.len(), 1, "{src}");
+    images.into_iter().next().unwrap().src
+}
+
+fn link(href: &str) -> Option<Url> {
+    let links = links(&format!(r#"<a href="{href}">link</a>"#));
+    assert_eq!(links.len(), 1, "{href}");
+    links.into_iter().next().unwrap()
+}
+
+#[test]
+fn web_and_mail_links_are_kept() {
+    for href in [
+        "https://example.com/page?q=1",
+        "http://example.com",
+        "mailto:alice@example.com",
+    ] {
+        assert_eq!(link(href), Some(Url::parse(href).unwrap()), "{href}");
+    }
+}
+
+#[test]
+fn surrounding_whitespace_is_ignored() {
+    assert_eq!(
+        link(" https://example.com "),
+        Some(Url::parse("https://example.com").unwrap())
+    );
+}
+
+#[test]
+fn other_schemes_are_rejected() {
+    for href in [
+        "javascript:alert(1)",
+        "JavaScript:alert(1)",
+        "data:text/html,<b>hi</b>",
+        "ftp://example.com",
+    ] {
+        assert_eq!(link(href), None, "{href}");
+    }
+}
+
+#[test]
+fn file_links_are_kept_unless_they_would_run() {
+    assert_eq!(
+        link("file:///etc/passwd"),
+        Some(Url::parse("file:///etc/passwd").unwrap())
+    );
+    for href in [
+        "file:///usr/share/applications/firefox.desktop",
+        "file:///tmp/Evil.DESKTOP",
+        "file://example.com/tmp/a.pdf",
+        "file:///bin/sh",
+    ] {
+        assert_eq!(link(href), None, "{href}");
+    }
+}
+
+#[test]
+fn relative_links_are_rejected() {
+    for href in ["page.html", "/etc/passwd", "//example.com", "", "#top"] {
+        assert_eq!(link(href), None, "{href}");
+    }
+}
+
+#[test]
+fn text_outside_links_has_no_link() {
+    assert_eq!(
+        links(r#"see <a href="https://example.com">this</a> page"#),
+        vec![None, Some(Url::parse("https://example.com").unwrap()), None]
+    );
+}
+
+#[test]
+fn img_absolute_paths_are_kept() {
+    assert_eq!(
+        html_to_images(r#"<p><img src="/tmp/a.png" alt="A"></p>"#),
+        vec![ImgTag {
+            src: Some(PathBuf::from("/tmp/a.png")),
+            alt: "A".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn img_file_urls_resolve_to_paths() {
+    assert_eq!(
+        img_src("file:///tmp/a%20b.png").as_deref(),
+        Some(Path::new("/tmp/a b.png"))
+    );
+    assert_eq!(
+        img_src("file://localhost/tmp/a.png").as_deref(),
+        Some(Path::new("/tmp/a.png"))
+    );
+    // Files on other hosts can't be read.
+    assert_eq!(img_src("file://example.com/tmp/a.png"), None);
+}
+
+#[test]
+fn img_relative_and_remote_sources_are_ignored() {
+    for src in [
+        "a.png",
+        "./a.png",
+        "../a.png",
+        "https://example.com/a.png",
+        "",
+    ] {
+        assert_eq!(img_src(src), None, "{src}");
+    }
+}
+
+#[test]
+fn img_without_src_keeps_alt() {
+    assert_eq!(
+        html_to_images(r#"<img alt="Missing">"#),
+        vec![ImgTag {
+            src: None,
+            alt: "Missing".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn images_split_the_text_where_they_appear() {
+    let blocks =
+        html_to_blocks(r#"Before <b>bold</b><img src="/a.png"> between <img src="/b.png">"#);
+    let blocks: Vec<_> = blocks
+        .iter()
+        .map(|block| match block {
+            BodyBlock::Text(spans) => spans.iter().map(|s| s.text.as_ref()).collect::<String>(),
+            BodyBlock::Image(nth) => format!("[{nth}]"),
+        })
+        .collect();
+    assert_eq!(blocks, ["Before bold", "[0]", " between ", "[1]"]);
+}
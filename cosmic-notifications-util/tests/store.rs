@@ -0,0 +1,219 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, SystemTime},
+};
+
+use cosmic_notifications_util::{
+    Hint, Notification,
+    store::{Effect, NotificationStore},
+};
+
+fn notification(id: u32, app_name: &str, urgency: u8) -> Notification {
+    let mut n = Notification::new(
+        app_name,
+        id,
+        "",
+        &format!("Notification {id}"),
+        "",
+        Vec::new(),
+        HashMap::new(),
+        -1,
+    );
+    n.hints.push(Hint::Urgency(urgency));
+    n.time = SystemTime::UNIX_EPOCH + Duration::from_secs(u64::from(id));
+    n
+}
+
+fn card_ids(store: &NotificationStore) -> Vec<u32> {
+    (0..store.visible())
+        .filter_map(|nth| store.card(nth))
+        .map(|n| n.id)
+        .collect()
+}
+
+#[test]
+fn push_shows_first_notification() {
+    let mut store = NotificationStore::new(VecDeque::new(), 3, 0);
+
+    assert_eq!(
+        store.push(notification(1, "a", 1)),
+        [Effect::Show, Effect::Reposition { visible: 1 }]
+    );
+    assert_eq!(
+        store.push(notification(2, "a", 1)),
+        [Effect::Reposition { visible: 2 }]
+    );
+    assert!(store.is_active(1));
+}
+
+#[test]
+fn visible_cards_are_limited() {
+    let mut store = NotificationStore::new(VecDeque::new(), 2, 0);
+    for id in 1..=3 {
+        store.push(notification(id, "a", 1));
+    }
+
+    assert_eq!(store.visible(), 2);
+    assert_eq!(card_ids(&store), [3, 2]);
+}
+
+#[test]
+fn cards_are_ordered_by_urgency_then_time() {
+    let mut store = NotificationStore::new(VecDeque::new(), 3, 0);
+    store.push(notification(1, "a", 2));
+    store.push(notification(2, "b", 0));
+    store.push(notification(3, "c", 1));
+
+    assert_eq!(card_ids(&store), [1, 3, 2]);
+}
+
+#[test]
+fn notifications_received_at_once_are_all_shown() {
+    let mut store = NotificationStore::new(VecDeque::new(), 3, 0);
+    for id in 1..=2 {
+        let mut n = notification(id, "a", 1);
+        n.time = SystemTime::UNIX_EPOCH;
+        store.push(n);
+    }
+
+    assert_eq!(card_ids(&store), [2, 1]);
+}
+
+#[test]
+fn expire_moves_to_history() {
+    let mut store = NotificationStore::new(VecDeque::new(), 3, 0);
+    store.push(notification(1, "a", 1));
+    store.push(notification(2, "a", 1));
+
+    assert_eq!(
+        store.expire(2),
+        [Effect::Reposition { visible: 1 }, Effect::Archived(2)]
+    );
+    assert_eq!(store.expire(1), [Effect::Hide, Effect::Archived(1)]);
+    assert!(store.expire(1).is_empty());
+    assert_eq!(
+        store.hidden().iter().map(|n| n.id).collect::<Vec<_>>(),
+        [1, 2]
+    );
+}
+
+#[test]
+fn archive_hides_replaced_card() {
+    let mut store = NotificationStore::new(VecDeque::new(), 3, 0);
+    store.push(notification(1, "a", 1));
+
+    assert_eq!(
+        store.archive(notification(1, "a", 1)),
+        [Effect::Hide, Effect::Archived(1)]
+    );
+    assert_eq!(
+        store.archive(notification(1, "a", 1)),
+        [Effect::Archived(1)]
+    );
+    assert_eq!(store.hidden().len(), 1);
+}
+
+#[test]
+fn close_removes_from_cards_and_history() {
+    let mut store = NotificationStore::new(VecDeque::from([notification(1, "a", 1)]), 3, 0);
+    store.push(notification(2, "a", 1));
+
+    assert_eq!(store.close(2), Some(vec![Effect::Hide]));
+    assert_eq!(store.close(1), Some(vec![Effect::HistoryChanged]));
+    assert_eq!(store.close(1), None);
+    assert!(store.get(1).is_none());
+}
+
+#[test]
+fn apps_exceeding_max_per_app_make_room() {
+    let mut store = NotificationStore::new(VecDeque::new(), 3, 2);
+    for id in 1..=3 {
+        store.push(notification(id, "a", 1));
+    }
+    store.push(notification(4, "b", 0));
+
+    assert_eq!(card_ids(&store), [3, 2, 4]);
+}
+
+#[test]
+fn critical_notifications_are_not_grouped() {
+    let mut store = NotificationStore::new(VecDeque::new(), 3, 1);
+    for id in 1..=3 {
+        store.push(notification(id, "a", 2));
+    }
+    store.push(notification(4, "b", 1));
+
+    assert_eq!(card_ids(&store), [3, 2, 1]);
+}
+
+#[test]
+fn set_limits_repositions_cards() {
+    let mut store = NotificationStore::new(VecDeque::new(), 3, 0);
+    for id in 1..=3 {
+        store.push(notification(id, "a", 1));
+    }
+
+    assert_eq!(store.set_limits(1, 0), [Effect::Reposition { visible: 1 }]);
+    assert!(store.set_limits(1, 0).is_empty());
+}
+
+#[test]
+fn replace_keeps_card() {
+    let mut store = NotificationStore::new(VecDeque::new(), 3, 0);
+    store.push(notification(1, "a", 1));
+    store.push(notification(2, "a", 1));
+
+    let mut replacement = notification(1, "a", 1);
+    replacement.summary = String::from("Updated");
+    replacement.time = SystemTime::now();
+    assert!(store.replace(replacement).is_empty());
+    assert_eq!(card_ids(&store), [2, 1]);
+    assert_eq!(store.card(1).unwrap().summary, "Updated");
+}
+
+#[test]
+fn replace_reorders_on_urgency_change() {
+    let mut store = NotificationStore::new(VecDeque::new(), 3, 0);
+    store.push(notification(1, "a", 1));
+    store.push(notification(2, "a", 1));
+
+    assert_eq!(
+        store.replace(notification(1, "a", 2)),
+        [Effect::Reposition { visible: 2 }]
+    );
+    assert_eq!(card_ids(&store), [1, 2]);
+}
+
+#[test]
+fn replace_shows_expired_notification_again() {
+    let mut store = NotificationStore::new(VecDeque::new(), 3, 0);
+    store.push(notification(1, "a", 1));
+    store.expire(1);
+
+    assert_eq!(
+        store.replace(notification(1, "a", 1)),
+        [
+            Effect::HistoryChanged,
+            Effect::Show,
+            Effect::Reposition { visible: 1 }
+        ]
+    );
+    assert!(store.is_active(1));
+    assert!(store.hidden().is_empty());
+}
+
+#[test]
+fn replace_updates_progress_in_place() {
+    let mut store = NotificationStore::new(VecDeque::new(), 3, 0);
+    let mut progress = notification(1, "a", 1);
+    progress.hints.push(Hint::Value(10));
+    store.push(progress.clone());
+    store.push(notification(2, "a", 1));
+
+    progress.hints = vec![Hint::Urgency(1), Hint::Value(100)];
+    assert!(store.replace(progress).is_empty());
+    assert_eq!(store.card(1).unwrap().value(), Some(100));
+
+    store.expire(1);
+    assert_eq!(store.get(1).unwrap().value(), Some(100));
+}
@@ -1,4 +1,4 @@
-use crate::subscriptions::notifications;
+use chrono::Local;
 use cosmic::app::{Core, Settings};
 use cosmic::core::Auto;
 use cosmic::cosmic_config::{Config, CosmicConfigEntry};
@@ -10,27 +10,42 @@ use cosmic::iced::platform_specific::shell::commands::popup::destroy_popup;
 use cosmic::iced::platform_specific::shell::wayland::commands::{
     activation,
     corner_radius::corner_radius,
-    layer_surface::{Anchor, KeyboardInteractivity, destroy_layer_surface, get_layer_surface},
+    layer_surface::{
+        Anchor, KeyboardInteractivity, destroy_layer_surface, get_layer_surface,
+        set_keyboard_interactivity,
+    },
 };
 use cosmic::iced::runtime::platform_specific::wayland::CornerRadius;
 use cosmic::iced::runtime::platform_specific::wayland::popup::{SctkPopupSettings, SctkPositioner};
-use cosmic::iced::widget::{column, rich_text, row, space};
+use cosmic::iced::widget::{
+    Row, column, container, mouse_area, progress_bar, rich_text, row, space,
+};
 use cosmic::iced::window::Id as SurfaceId;
-use cosmic::iced::{self, Length, Limits, Subscription, id};
+use cosmic::iced::{self, ContentFit, Length, Limits, Subscription, id};
 use cosmic::surface;
 use cosmic::surface::action::LiveSettings;
-use cosmic::widget::{autosize, button, icon, text};
+use cosmic::widget::{self, autosize, button, icon, text};
 use cosmic::{Application, Element, app::Task};
+use cosmic_notifications::desktop::{self, Applications};
+use cosmic_notifications::dnd::{self, Dnd};
+use cosmic_notifications::expiry::{self, Timers};
+use cosmic_notifications::history::{self, History};
+use cosmic_notifications::sound::{self, Sounds};
+use cosmic_notifications::subscriptions::{
+    history as history_dbus, idle, notifications, screencast, toplevels,
+};
 use cosmic_notifications_config::NotificationsConfig;
-use cosmic_notifications_util::markup::html_to_spans;
-use cosmic_notifications_util::{ActionId, CloseReason, Notification};
+use cosmic_notifications_util::markup::{self, BodyBlock, html_to_blocks};
+use cosmic_notifications_util::store::{Effect, NotificationStore};
+use cosmic_notifications_util::{ActionId, CloseReason, INLINE_REPLY, Image, Notification};
 use cosmic_panel_config::{CosmicPanelConfig, CosmicPanelOuput, PanelAnchor};
 use enumflags2::BitFlags;
 use iced::Alignment;
 use std::borrow::Cow;
-use std::collections::VecDeque;
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
+use url::Url;
 
 static NOTIFICATIONS_APPLET: &str = "com.system76.CosmicAppletNotifications";
 
@@ -54,8 +69,25 @@ struct CosmicNotifications {
     active_surface: bool,
     window_id: SurfaceId,
     pending_notifications: Vec<Notification>,
-    cards: Vec<Notification>,
-    hidden: VecDeque<Notification>,
+    store: NotificationStore,
+    timers: Timers,
+    /// The popup under the pointer. Cards move between popups as others come
+    /// and go, so the hovered notification follows the popup.
+    hovered_popup: Option<usize>,
+    /// The notification under the pointer, whose timer is paused.
+    hovered: Option<u32>,
+    /// The notification being replied to inline, and the text typed so far.
+    reply: Option<(u32, String)>,
+    reply_id: id::Id,
+    history: Option<History>,
+    /// Ids of the history loaded at startup, which new notifications must not reuse.
+    restored_ids: Arc<[u32]>,
+    dnd: Dnd,
+    /// Whether a fullscreen window is shown on the output notifications appear on.
+    fullscreen: bool,
+    screencast_active: bool,
+    sounds: Sounds,
+    applications: Applications,
     notifications_id: id::Id,
     notifications_tx: Option<mpsc::Sender<notifications::Input>>,
     config: NotificationsConfig,
@@ -68,10 +100,23 @@ struct CosmicNotifications {
 #[derive(Debug, Clone)]
 enum Message {
     ActivateNotification(u32),
+    InvokeAction(u32, ActionId),
+    OpenLink(Url),
+    OpenLinkToken(Option<String>, Url),
     ActivationToken(Option<String>, u32, Option<ActionId>),
     Dismissed(u32),
     Notification(notifications::Event),
-    Timeout(u32),
+    Timeout(expiry::Timeout),
+    Hover(usize, bool),
+    Idle(bool),
+    Screencast(bool),
+    PopupClosed(SurfaceId),
+    StartReply(u32),
+    ReplyInput(String),
+    SendReply,
+    CancelReply,
+    DndTick,
+    Fullscreen(bool),
     Config(NotificationsConfig),
     PanelConfig(CosmicPanelConfig),
     DockConfig(CosmicPanelConfig),
@@ -81,71 +126,245 @@ enum Message {
 }
 
 impl CosmicNotifications {
-    fn expire(&mut self, i: u32) -> Task<Message> {
-        let Some((c_pos, _)) = self.cards.iter().enumerate().find(|(_, n)| n.id == i) else {
-            return Task::none();
+    fn close(&mut self, i: u32, reason: CloseReason) -> Option<Task<Message>> {
+        let effects = self.store.close(i)?;
+        self.timers.cancel(i);
+        let stop_reply = if self.reply.as_ref().is_some_and(|(id, _)| *id == i) {
+            self.stop_reply()
+        } else {
+            Task::none()
         };
 
-        let notification = self.cards.remove(c_pos);
-        self.sort_notifications();
-        self.group_notifications();
-        self.hidden.push_front(notification);
-        self.hidden.truncate(200);
-        let mut tasks = Vec::with_capacity(1);
-        if self.popups.len() > self.cards.len() {
-            let to_remove = self.popups.len() - self.cards.len();
-            for _ in 0..to_remove {
-                let (id, _, _) = self.popups.remove(self.popups.len() - 1);
-                tasks.push(destroy_popup::<Message>(id).discard());
+        if let Some(sender) = &self.notifications_tx {
+            let sender = sender.clone();
+            tokio::spawn(async move {
+                // The server signals closes the client asked for itself.
+                if reason != CloseReason::CloseNotification {
+                    _ = sender.send(notifications::Input::Closed(i, reason)).await;
+                }
+                sender.send(notifications::Input::Removed(i)).await
+            });
+        }
+
+        Some(Task::batch([stop_reply, self.apply(effects)]))
+    }
+
+    /// Stops replying inline, giving up the keyboard focus.
+    fn stop_reply(&mut self) -> Task<Message> {
+        if self.reply.take().is_none() {
+            return Task::none();
+        }
+        Task::batch([
+            set_keyboard_interactivity(self.window_id, KeyboardInteractivity::None),
+            self.recreate_popups(),
+        ])
+    }
+
+    /// Recreates the popups of the visible cards. Popups only receive the
+    /// keyboard focus with a grab, which is requested when they are created,
+    /// so they grab while a reply is being typed.
+    fn recreate_popups(&mut self) -> Task<Message> {
+        let mut tasks: Vec<_> = self
+            .popups
+            .drain(..)
+            .rev()
+            .map(|(id, _, _)| destroy_popup::<Message>(id).discard())
+            .collect();
+        if self.active_surface {
+            tasks.push(self.reposition(self.store.visible()));
+        }
+        Task::batch(tasks)
+    }
+
+    /// Keeps the notification in the history without showing it, hiding the
+    /// card it replaces if there is one.
+    fn hide(&mut self, notification: Notification) -> Task<Message> {
+        self.timers.cancel(notification.id);
+        let effects = self.store.archive(notification);
+        self.apply(effects)
+    }
+
+    /// Carries out the effects of a change to the notification store.
+    fn apply(&mut self, effects: Vec<Effect>) -> Task<Message> {
+        let mut tasks = Vec::with_capacity(effects.len());
+        for effect in effects {
+            match effect {
+                Effect::Show => {
+                    if !self.active_surface {
+                        self.active_surface = true;
+                        tasks.push(self.layer_surface());
+                    }
+                }
+                Effect::Hide => {
+                    if self.active_surface {
+                        self.active_surface = false;
+                        self.popups.clear();
+                        tasks.push(destroy_layer_surface(self.window_id));
+                    }
+                }
+                Effect::Reposition { visible } => {
+                    tasks.push(self.reposition(visible));
+                }
+                Effect::Archived(_) | Effect::HistoryChanged => self.save_history(),
             }
         }
-        return Task::batch(tasks);
+        tasks.push(self.sync_hover());
+        Task::batch(tasks)
     }
 
-    fn close(&mut self, i: u32, reason: CloseReason) -> Option<Task<Message>> {
-        let c_pos = self.cards.iter().position(|n| n.id == i);
-        let notification = c_pos.map(|c_pos| self.cards.remove(c_pos)).or_else(|| {
-            self.hidden
-                .iter()
-                .position(|n| n.id == i)
-                .and_then(|pos| self.hidden.remove(pos))
-        })?;
+    /// Pauses the notification in the hovered popup, and resumes the one which
+    /// was hovered before if it is no longer under the pointer.
+    fn sync_hover(&mut self) -> Task<Message> {
+        // Popups which were destroyed do not report the pointer leaving.
+        if self
+            .hovered_popup
+            .is_some_and(|nth| nth >= self.popups.len())
+        {
+            self.hovered_popup = None;
+        }
+        let hovered = self
+            .hovered_popup
+            .and_then(|nth| self.store.card(nth))
+            .map(|n| n.id);
+        if hovered == self.hovered {
+            return Task::none();
+        }
 
-        if self.cards.is_empty() {
-            self.cards.shrink_to(50);
+        let now = Instant::now();
+        let previous = std::mem::replace(&mut self.hovered, hovered);
+        if let Some(id) = hovered {
+            self.timers.pause(id, now);
+        }
+        match previous {
+            Some(id) if !self.reply.as_ref().is_some_and(|(reply, _)| *reply == id) => {
+                schedule(self.timers.resume(id, now))
+            }
+            _ => Task::none(),
         }
+    }
 
-        self.sort_notifications();
-        self.group_notifications();
-        if let Some(sender) = &self.notifications_tx {
-            let id = notification.id;
-            let sender = sender.clone();
+    /// Destroys the popups of cards which are no longer visible, or adds a
+    /// popup if more cards are visible.
+    fn reposition(&mut self, visible: usize) -> Task<Message> {
+        let mut tasks = Vec::with_capacity(1);
+        while self.popups.len() > visible {
+            let (id, _, _) = self.popups.remove(self.popups.len() - 1);
+            tasks.push(destroy_popup::<Message>(id).discard());
+        }
+        // Popups are positioned relative to the previous one, so the next one
+        // is added once the size of the last one is known.
+        if self.popups.len() < visible && self.popups.last().is_none_or(|p| p.2.is_some()) {
+            tasks.push(self.add_popup());
+        }
+        Task::batch(tasks)
+    }
+
+    /// Pauses or resumes the expiry of all notifications.
+    fn set_idle(&mut self, idle: bool) -> Vec<Task<Message>> {
+        // The notification being replied to stays paused like a hovered one.
+        let paused = self.reply.as_ref().map(|(id, _)| *id).or(self.hovered);
+        self.timers
+            .set_idle(idle, Instant::now(), paused)
+            .into_iter()
+            .map(|timeout| schedule(Some(timeout)))
+            .collect()
+    }
+
+    fn held_by_dnd(&self, notification: &Notification) -> bool {
+        self.dnd.is_active() && !dnd::allowed(&self.config, notification)
+    }
+
+    /// Whether Do Not Disturb is activated by a fullscreen window or a screencast.
+    fn auto_dnd(&self) -> bool {
+        (self.config.do_not_disturb_fullscreen && self.fullscreen)
+            || (self.config.do_not_disturb_screencast && self.screencast_active)
+    }
+
+    fn update_dnd(&mut self) {
+        let mut dnd = dnd::state(&self.config, Local::now());
+        if !dnd.is_active() && self.auto_dnd() {
+            dnd = Dnd::On { until: None };
+        }
+        if dnd == self.dnd {
+            return;
+        }
+        self.dnd = dnd;
+        if let Some(sender) = self.notifications_tx.clone() {
             tokio::spawn(async move {
-                _ = sender.send(notifications::Input::Closed(id, reason));
+                _ = sender.send(notifications::Input::DoNotDisturb(dnd)).await;
             });
         }
+    }
 
-        if let Some(sender) = &self.notifications_tx {
-            let sender = sender.clone();
-            let id = notification.id;
-            tokio::spawn(async move { sender.send(notifications::Input::Dismissed(id)).await });
+    fn save_history(&mut self) {
+        let removed = history::retain(self.store.hidden_mut(), &self.config);
+        self.forget(removed);
+        if let Some(history) = &self.history {
+            history.save(self.store.hidden());
         }
+    }
 
-        if self.cards.is_empty() && self.active_surface {
-            self.active_surface = false;
-            self.popups.clear();
-            Some(destroy_layer_surface(self.window_id))
-        } else if self.popups.len() > self.cards.len() {
-            let to_remove = self.popups.len() - self.cards.len();
-            let mut tasks = Vec::with_capacity(1);
-            for _ in 0..to_remove {
-                let (id, _, _) = self.popups.remove(self.popups.len() - 1);
-                tasks.push(destroy_popup::<Message>(id).discard());
-            }
-            Some(Task::batch(tasks))
-        } else {
-            Some(Task::none())
+    /// Lets the server forget notifications which are neither displayed nor
+    /// in the history anymore.
+    fn forget(&self, ids: Vec<u32>) {
+        if ids.is_empty() {
+            return;
+        }
+        if let Some(sender) = self.notifications_tx.clone() {
+            tokio::spawn(async move {
+                for id in ids {
+                    _ = sender.send(notifications::Input::Removed(id)).await;
+                }
+            });
+        }
+    }
+
+    fn history_request(&mut self, request: history_dbus::Request) -> Task<Message> {
+        match request {
+            history_dbus::Request::ListActive(reply) => {
+                reply.send(self.store.active().to_vec());
+            }
+            history_dbus::Request::ListHistory {
+                offset,
+                limit,
+                reply,
+            } => {
+                reply.send(
+                    self.store
+                        .hidden()
+                        .iter()
+                        .skip(offset)
+                        .take(limit)
+                        .cloned()
+                        .collect(),
+                );
+            }
+            history_dbus::Request::Get(id, reply) => {
+                reply.send(self.store.get(id).cloned());
+            }
+            history_dbus::Request::Remove(id, reply) => {
+                let task = self.close(id, CloseReason::Dismissed);
+                reply.send(task.is_some());
+                return task.unwrap_or(Task::none());
+            }
+            history_dbus::Request::Clear(reply) => {
+                let ids = self.store.clear_history();
+                self.save_history();
+                self.forget(ids);
+                reply.send(());
+            }
+            history_dbus::Request::InvokeAction { id, action, reply } => {
+                let known = self
+                    .store
+                    .get(id)
+                    .is_some_and(|n| n.actions.iter().any(|(a, _)| *a == action));
+                reply.send(known);
+                if known {
+                    return self.request_activation(id, Some(action));
+                }
+            }
         }
+        Task::none()
     }
 
     fn anchor_for_notification_applet(&self) -> (Anchor, Option<String>) {
@@ -280,360 +499,379 @@ impl CosmicNotifications {
             self.pending_notifications.push(notification);
             return Task::none();
         }
-        let mut timeout = u32::try_from(notification.expire_timeout).unwrap_or(3000);
-        let max_timeout = if notification.urgency() == 2 {
-            self.config.max_timeout_urgent
-        } else if notification.urgency() == 1 {
-            self.config.max_timeout_normal
-        } else {
-            self.config.max_timeout_low
-        }
-        .unwrap_or(u32::try_from(notification.expire_timeout).unwrap_or(3000));
-        timeout = timeout.min(max_timeout);
+        let timeout = expiry::timeout(&self.config, &notification);
+        let timer = self.timers.start(notification.id, timeout, Instant::now());
+        let effects = self.store.push(notification);
+        iced::Task::batch([schedule(timer), self.apply(effects)])
+    }
 
-        let mut tasks = vec![if timeout > 0 {
-            iced::Task::perform(
-                tokio::time::sleep(Duration::from_millis(timeout as u64)),
-                move |_| cosmic::action::app(Message::Timeout(notification.id)),
+    fn layer_surface(&self) -> Task<Message> {
+        let (anchor, _output) = self.anchor.clone().unwrap_or((Anchor::TOP, None));
+        get_layer_surface(SctkLayerSurfaceSettings {
+            id: self.window_id,
+            anchor,
+            exclusive_zone: 0,
+            keyboard_interactivity: KeyboardInteractivity::None,
+            namespace: "notifications".to_string(),
+            margin: IcedMargin {
+                top: 8,
+                right: 8,
+                bottom: 8,
+                left: 8,
+            },
+            size: Some((Some(300), Some(1))),
+            output: IcedOutput::Active, // TODO should we only create the notification on the output the applet is on?
+            size_limits: Limits::NONE
+                .min_width(300.0)
+                .min_height(1.0)
+                .max_height(1920.0)
+                .max_width(300.0),
+            ..Default::default()
+        })
+    }
+
+    /// Adds a popup for the next visible card, below the last popup.
+    fn add_popup(&mut self) -> Task<Message> {
+        let mut tasks = Vec::with_capacity(2);
+        let (parent, positioner) = if self.popups.is_empty() {
+            (
+                self.window_id,
+                SctkPositioner {
+                    size: None,
+                    size_limits: Limits::NONE
+                        .min_width(300.0)
+                        .min_height(1.0)
+                        .max_height(1920.0)
+                        .max_width(300.0),
+                    anchor_rect: iced::Rectangle {
+                        x: 0,
+                        y: 6,
+                        width: 300,
+                        height: 1,
+                    },
+                    anchor: if self
+                        .anchor
+                        .as_ref()
+                        .is_some_and(|a| a.0.contains(Anchor::BOTTOM))
+                    {
+                        cosmic::cctk::sctk::reexports::protocols::xdg::shell::client::xdg_positioner::Anchor::Top
+                    } else {
+                        cosmic::cctk::sctk::reexports::protocols::xdg::shell::client::xdg_positioner::Anchor::Bottom
+                    },
+                    gravity: if self
+                        .anchor
+                        .as_ref()
+                        .is_some_and(|a| a.0.contains(Anchor::BOTTOM))
+                    {
+                        cosmic::cctk::sctk::reexports::protocols::xdg::shell::client::xdg_positioner::Gravity::Top
+                    } else {
+                        cosmic::cctk::sctk::reexports::protocols::xdg::shell::client::xdg_positioner::Gravity::Bottom
+                    },
+                    constraint_adjustment: 0, // TODO do we want to allow sliding if there is no other way?
+                    offset: if self
+                        .anchor
+                        .as_ref()
+                        .is_some_and(|a| a.0.contains(Anchor::BOTTOM))
+                    {
+                        (0, -8)
+                    } else {
+                        (0, 8)
+                    },
+                    reactive: true,
+                },
             )
         } else {
-            iced::Task::none()
-        }];
-
-        if self.cards.is_empty() && !self.config.do_not_disturb {
-            let (anchor, _output) = self.anchor.clone().unwrap_or((Anchor::TOP, None));
-            self.active_surface = true;
-            tasks.push(get_layer_surface(SctkLayerSurfaceSettings {
-                id: self.window_id,
-                anchor,
-                exclusive_zone: 0,
-                keyboard_interactivity: KeyboardInteractivity::None,
-                namespace: "notifications".to_string(),
-                margin: IcedMargin {
-                    top: 8,
-                    right: 8,
-                    bottom: 8,
-                    left: 8,
+            let (p_id, _, p_size) = self.popups.last().unwrap();
+            (
+                *p_id,
+                SctkPositioner {
+                    size: None,
+                    size_limits: Limits::NONE
+                        .min_width(300.0)
+                        .min_height(1.0)
+                        .max_height(1920.0)
+                        .max_width(300.0),
+                    anchor_rect: p_size
+                        .map(|s| iced::Rectangle {
+                            x: 0,
+                            y: 0,
+                            width: s.width as i32,
+                            height: s.height as i32,
+                        })
+                        .unwrap_or_default(),
+                    anchor: if self
+                        .anchor
+                        .as_ref()
+                        .is_some_and(|a| a.0.contains(Anchor::BOTTOM))
+                    {
+                        cosmic::cctk::sctk::reexports::protocols::xdg::shell::client::xdg_positioner::Anchor::Top
+                    } else {
+                        cosmic::cctk::sctk::reexports::protocols::xdg::shell::client::xdg_positioner::Anchor::Bottom
+                    },
+                    gravity: if self
+                        .anchor
+                        .as_ref()
+                        .is_some_and(|a| a.0.contains(Anchor::BOTTOM))
+                    {
+                        cosmic::cctk::sctk::reexports::protocols::xdg::shell::client::xdg_positioner::Gravity::Top
+                    } else {
+                        cosmic::cctk::sctk::reexports::protocols::xdg::shell::client::xdg_positioner::Gravity::Bottom
+                    },
+                    constraint_adjustment: 0, // TODO do we want to allow sliding if there is no other way?
+                    offset: if self
+                        .anchor
+                        .as_ref()
+                        .is_some_and(|a| a.0.contains(Anchor::BOTTOM))
+                    {
+                        (0, -8)
+                    } else {
+                        (0, 8)
+                    },
+                    reactive: true,
                 },
-                size: Some((Some(300), Some(1))),
-                output: IcedOutput::Active, // TODO should we only create the notification on the output the applet is on?
-                size_limits: Limits::NONE
-                    .min_width(300.0)
-                    .min_height(1.0)
-                    .max_height(1920.0)
-                    .max_width(300.0),
-                ..Default::default()
-            }));
+            )
+        };
+        let p_id = SurfaceId::unique();
+        let settings = SctkPopupSettings {
+            parent,
+            id: p_id,
+            positioner,
+            parent_size: None,
+            grab: self.reply.is_some(),
+            close_with_children: false,
+            input_zone: None,
         };
 
-        self.sort_notifications();
+        let auto_id = iced::id::Id::unique();
+        let nth = self.popups.len();
+        self.popups.push((p_id, auto_id.clone(), None));
+        tasks.push(cosmic::surface::surface_task(
+            cosmic::surface::action::app_popup(
+                |_| LiveSettings::default(),
+                move |_: &mut CosmicNotifications| settings.clone(),
+                Some(Box::new(move |app: &CosmicNotifications| {
+                    let Some(autosize_id) = app.popups.get(nth) else {
+                        return space::horizontal().height(300).into();
+                    };
+                    let Some(n) = app.store.card(nth) else {
+                        return space::horizontal().height(300).into();
+                    };
 
-        match self
-            .cards
-            .binary_search_by(|a| match a.urgency().cmp(&notification.urgency()) {
-                std::cmp::Ordering::Equal => a.time.cmp(&notification.time),
-                other => other,
-            }) {
-            Ok(pos) => {
-                self.cards[pos] = notification;
-            }
-            Err(pos) => {
-                self.cards.insert(pos, notification);
-            }
-        };
-        self.group_notifications();
-        if self.popups.len() < self.config.max_notifications as usize
-            && self.cards.len() > self.popups.len()
-        {
-            let (parent, positioner) = if self.popups.is_empty() {
-                (
-                    self.window_id,
-                    SctkPositioner {
-                        size: None,
-                        size_limits: Limits::NONE
-                            .min_width(300.0)
-                            .min_height(1.0)
-                            .max_height(1920.0)
-                            .max_width(300.0),
-                        anchor_rect: iced::Rectangle {
-                            x: 0,
-                            y: 6,
-                            width: 300,
-                            height: 1,
-                        },
-                        anchor: if self
-                            .anchor
-                            .as_ref()
-                            .is_some_and(|a| a.0.contains(Anchor::BOTTOM))
-                        {
-                            cosmic::cctk::sctk::reexports::protocols::xdg::shell::client::xdg_positioner::Anchor::Top
-                        } else {
-                            cosmic::cctk::sctk::reexports::protocols::xdg::shell::client::xdg_positioner::Anchor::Bottom
-                        },
-                        gravity: if self
-                            .anchor
-                            .as_ref()
-                            .is_some_and(|a| a.0.contains(Anchor::BOTTOM))
-                        {
-                            cosmic::cctk::sctk::reexports::protocols::xdg::shell::client::xdg_positioner::Gravity::Top
-                        } else {
-                            cosmic::cctk::sctk::reexports::protocols::xdg::shell::client::xdg_positioner::Gravity::Bottom
-                        },
-                        constraint_adjustment: 0, // TODO do we want to allow sliding if there is no other way?
-                        offset: if self
-                            .anchor
-                            .as_ref()
-                            .is_some_and(|a| a.0.contains(Anchor::BOTTOM))
-                        {
-                            (0, -8)
-                        } else {
-                            (0, 8)
-                        },
-                        reactive: true,
-                    },
-                )
-            } else {
-                let (p_id, _, p_size) = self.popups.last().unwrap();
-                (
-                    *p_id,
-                    SctkPositioner {
-                        size: None,
-                        size_limits: Limits::NONE
-                            .min_width(300.0)
-                            .min_height(1.0)
-                            .max_height(1920.0)
-                            .max_width(300.0),
-                        anchor_rect: p_size
-                            .map(|s| iced::Rectangle {
-                                x: 0,
-                                y: 0,
-                                width: s.width as i32,
-                                height: s.height as i32,
-                            })
-                            .unwrap_or_default(),
-                        anchor: if self
-                            .anchor
-                            .as_ref()
-                            .is_some_and(|a| a.0.contains(Anchor::BOTTOM))
-                        {
-                            cosmic::cctk::sctk::reexports::protocols::xdg::shell::client::xdg_positioner::Anchor::Top
-                        } else {
-                            cosmic::cctk::sctk::reexports::protocols::xdg::shell::client::xdg_positioner::Anchor::Bottom
-                        },
-                        gravity: if self
-                            .anchor
-                            .as_ref()
-                            .is_some_and(|a| a.0.contains(Anchor::BOTTOM))
-                        {
-                            cosmic::cctk::sctk::reexports::protocols::xdg::shell::client::xdg_positioner::Gravity::Top
-                        } else {
-                            cosmic::cctk::sctk::reexports::protocols::xdg::shell::client::xdg_positioner::Gravity::Bottom
-                        },
-                        constraint_adjustment: 0, // TODO do we want to allow sliding if there is no other way?
-                        offset: if self
-                            .anchor
-                            .as_ref()
-                            .is_some_and(|a| a.0.contains(Anchor::BOTTOM))
-                        {
-                            (0, -8)
-                        } else {
-                            (0, 8)
-                        },
-                        reactive: true,
-                    },
-                )
-            };
-            let p_id = SurfaceId::unique();
-            let settings = SctkPopupSettings {
-                parent,
-                id: p_id,
-                positioner,
-                parent_size: None,
-                grab: false,
-                close_with_children: false,
-                input_zone: None,
-            };
+                    let id = n.id;
+                    let card_list = cosmic::widget::cards(
+                        app.notifications_id.clone(),
+                        vec![app.notification_card(n)],
+                        Message::Ignore,
+                        None::<fn(bool) -> Message>,
+                        Some(move |_| Message::ActivateNotification(id)),
+                        "",
+                        "",
+                        "",
+                        None,
+                        true,
+                    )
+                    .width(Length::Fixed(300.));
+                    // The card does not expire while the pointer is over it.
+                    let card_list = mouse_area(card_list)
+                        .on_enter(Message::Hover(nth, true))
+                        .on_exit(Message::Hover(nth, false));
 
-            let auto_id = iced::id::Id::unique();
-            let nth = self.popups.len();
-            self.popups.push((p_id, auto_id.clone(), None));
-            tasks.push(cosmic::surface::surface_task(
-                cosmic::surface::action::app_popup(
-                    |_| LiveSettings::default(),
-                    move |_: &mut CosmicNotifications| settings.clone(),
-                    Some(Box::new(move |app: &CosmicNotifications| {
-                        let Some(autosize_id) = app.popups.get(nth) else {
-                            return space::horizontal().height(300).into();
-                        };
-                        if app.cards.len() <= nth {
-                            return space::horizontal().height(300).into();
-                        }
+                    Element::from(
+                        autosize::autosize(card_list, autosize_id.1.clone())
+                            .min_width(200.)
+                            .min_height(100.)
+                            .max_width(300.)
+                            .max_height(1920.),
+                    )
+                    .map(cosmic::Action::App)
+                })),
+            ),
+        ));
+        let rad_xs = self.core.system_theme().cosmic().radius_xs();
 
-                        let (ids, notif_elems) = app
-                            .cards
-                            .iter()
-                            .rev()
-                            .map(|n| {
-                                let app_name = text::caption(if n.app_name.len() > 24 {
-                                    Cow::from(format!(
-                                        "{:.26}...",
-                                        n.app_name.lines().next().unwrap_or_default()
-                                    ))
-                                } else {
-                                    Cow::from(&n.app_name)
-                                })
-                                .width(Length::Fill);
-
-                                let close_notif = button::custom(
-                                    icon::from_name("window-close-symbolic")
-                                        .size(16)
-                                        .symbolic(true),
-                                )
-                                .on_press(Message::Dismissed(n.id))
-                                .class(cosmic::theme::Button::Text);
-
-                                let e = Element::from(
-                                    column!(
-                                        if let Some(icon) = n.notification_icon() {
-                                            row![icon.size(16), app_name, close_notif]
-                                                .spacing(8)
-                                                .align_y(Alignment::Center)
-                                        } else {
-                                            row![app_name, close_notif]
-                                                .spacing(8)
-                                                .align_y(Alignment::Center)
-                                        },
-                                        column![
-                                            text::body(
-                                                n.summary.lines().next().unwrap_or_default()
-                                            )
-                                            .width(Length::Fill),
-                                            Element::from(
-                                                rich_text(html_to_spans(&n.body)).size(12.0)
-                                            )
-                                            .map(|_: ()| Message::Ignore)
-                                        ]
-                                    )
-                                    .width(Length::Fill),
-                                );
-                                (n.id, e)
-                            })
-                            .nth(nth)
-                            .unzip();
-
-                        let card_list = cosmic::widget::cards(
-                            app.notifications_id.clone(),
-                            notif_elems.into_iter().collect(),
-                            Message::Ignore,
-                            None::<fn(bool) -> Message>,
-                            Some(move |_| Message::ActivateNotification(ids.unwrap())),
-                            "",
-                            "",
-                            "",
-                            None,
-                            true,
-                        )
-                        .width(Length::Fixed(300.));
-
-                        Element::from(
-                            autosize::autosize(card_list, autosize_id.1.clone())
-                                .min_width(200.)
-                                .min_height(100.)
-                                .max_width(300.)
-                                .max_height(1920.),
-                        )
-                        .map(cosmic::Action::App)
-                    })),
-                ),
-            ));
-            let rad_xs = self.core.system_theme().cosmic().radius_xs();
-
-            tasks.push(
-                corner_radius(
-                    p_id,
-                    // TODO use the theme for this and the cards...
-                    Some(CornerRadius {
-                        top_left: rad_xs[0].round() as u32,
-                        top_right: rad_xs[1].round() as u32,
-                        bottom_left: rad_xs[2].round() as u32,
-                        bottom_right: rad_xs[3].round() as u32,
-                    }),
-                )
-                .discard(),
-            );
-        }
+        tasks.push(
+            corner_radius(
+                p_id,
+                // TODO use the theme for this and the cards...
+                Some(CornerRadius {
+                    top_left: rad_xs[0].round() as u32,
+                    top_right: rad_xs[1].round() as u32,
+                    bottom_left: rad_xs[2].round() as u32,
+                    bottom_right: rad_xs[3].round() as u32,
+                }),
+            )
+            .discard(),
+        );
 
         iced::Task::batch(tasks)
     }
 
-    fn group_notifications(&mut self) {
-        if self.config.max_per_app == 0 {
-            return;
-        }
+    fn notification_card<'a>(&'a self, n: &'a Notification) -> Element<'a, Message> {
+        let display_name = n.display_name();
+        let app_name = text::caption(if display_name.chars().count() > 24 {
+            Cow::from(format!(
+                "{:.26}...",
+                display_name.lines().next().unwrap_or_default()
+            ))
+        } else {
+            Cow::from(display_name)
+        })
+        .width(Length::Fill);
 
-        let mut extra_per_app = Vec::new();
-        let mut cur_count = 0;
-        let Some(mut cur_id) = self.cards.first().map(|n| n.app_name.clone()) else {
-            return;
-        };
-        self.cards = self
-            .cards
-            .drain(..)
-            .filter(|n| {
-                if n.app_name == cur_id {
-                    cur_count += 1;
-                } else {
-                    cur_count = 1;
-                    cur_id = n.app_name.clone();
+        let close_notif = button::custom(
+            icon::from_name("window-close-symbolic")
+                .size(16)
+                .symbolic(true),
+        )
+        .on_press(Message::Dismissed(n.id))
+        .class(cosmic::theme::Button::Text);
+
+        let mut body =
+            column![text::body(n.summary.lines().next().unwrap_or_default()).width(Length::Fill)];
+        // Images are shown where their tag is in the body.
+        for block in html_to_blocks(&n.body) {
+            match block {
+                BodyBlock::Text(spans) => {
+                    body = body
+                        .push(Element::from(rich_text(spans).size(12.0)).map(Message::OpenLink));
                 }
-                if cur_count > self.config.max_per_app {
-                    extra_per_app.push(n.clone());
-                    false
-                } else {
-                    true
+                BodyBlock::Image(nth) => {
+                    let Some(body_image) = n.body_images.get(nth) else {
+                        continue;
+                    };
+                    match &body_image.image {
+                        Some(Image::Data {
+                            width,
+                            height,
+                            data,
+                        }) => {
+                            body = body.push(
+                                widget::image(widget::image::Handle::from_rgba(
+                                    *width,
+                                    *height,
+                                    data.clone(),
+                                ))
+                                .content_fit(ContentFit::ScaleDown),
+                            );
+                        }
+                        // Images of notifications restored from the history are stored as files.
+                        Some(Image::File(path)) => {
+                            body = body.push(
+                                widget::image(widget::image::Handle::from_path(path))
+                                    .content_fit(ContentFit::ScaleDown),
+                            );
+                        }
+                        _ if !body_image.alt.is_empty() => {
+                            body = body.push(text::caption(body_image.alt.as_str()));
+                        }
+                        _ => {}
+                    }
                 }
-            })
-            .collect();
-
-        for n in extra_per_app {
-            if self.cards.len() < self.config.max_notifications as usize {
-                self.insert_sorted(n);
-            } else {
-                self.cards.push(n);
             }
         }
-    }
+        if let Some(value) = n.value() {
+            body = body.push(
+                container(progress_bar(0.0..=100.0, f32::from(value)).girth(6)).padding([4, 0]),
+            );
+        }
 
-    fn insert_sorted(&mut self, notification: Notification) {
-        match self
-            .cards
-            .binary_search_by(|a| match notification.urgency().cmp(&a.urgency()) {
-                std::cmp::Ordering::Equal => notification.time.cmp(&a.time),
-                other => other,
-            }) {
-            Ok(pos) => {
-                self.cards[pos] = notification;
-            }
-            Err(pos) => {
-                self.cards.insert(pos, notification);
-            }
+        // The default action is invoked by clicking the card itself, and the
+        // inline reply action by sending a reply.
+        let mut actions: Vec<Element<'a, Message>> = n
+            .actions
+            .iter()
+            .filter(|(action, _)| match action {
+                ActionId::Default => false,
+                ActionId::Custom(key) => key != INLINE_REPLY,
+            })
+            .map(|(action, label)| {
+                let message = Message::InvokeAction(n.id, action.clone());
+                // With the "action-icons" hint, action keys name icons, which
+                // were looked up when the notification arrived.
+                if let Some(path) = n.action_icon(action) {
+                    let symbolic = action.to_string().ends_with("-symbolic");
+                    return button::icon(icon::from_path(path.clone()).symbolic(symbolic))
+                        .tooltip(label.as_str())
+                        .on_press(message)
+                        .into();
+                }
+                button::standard(label.as_str()).on_press(message).into()
+            })
+            .collect();
+        let reply = self.reply.as_ref().filter(|(id, _)| *id == n.id);
+        if let Some(label) = n.inline_reply()
+            && reply.is_none()
+        {
+            actions.push(
+                button::standard(label)
+                    .on_press(Message::StartReply(n.id))
+                    .into(),
+            );
+        }
+        if !actions.is_empty() {
+            body = body.push(
+                Row::with_children(actions)
+                    .spacing(8)
+                    .padding([8, 0, 0, 0])
+                    .wrap(),
+            );
+        }
+        if let Some(label) = n.inline_reply()
+            && let Some((_, text)) = reply
+        {
+            body = body.push(
+                row![
+                    widget::text_input(n.reply_placeholder().unwrap_or(label), text.as_str())
+                        .id(self.reply_id.clone())
+                        .on_input(Message::ReplyInput)
+                        .on_submit(|_| Message::SendReply)
+                        .width(Length::Fill),
+                    button::icon(
+                        icon::from_name("mail-send-symbolic")
+                            .size(16)
+                            .symbolic(true)
+                    )
+                    .tooltip(label)
+                    .on_press(Message::SendReply),
+                ]
+                .spacing(8)
+                .padding([8, 0, 0, 0])
+                .align_y(Alignment::Center),
+            );
         }
-    }
 
-    fn sort_notifications(&mut self) {
-        self.cards
-            .sort_by(|a, b| match a.urgency().cmp(&b.urgency()) {
-                std::cmp::Ordering::Equal => a.time.cmp(&b.time),
-                other => other,
-            });
+        Element::from(
+            column!(
+                if let Some(icon) = n.notification_icon() {
+                    row![icon.size(16), app_name, close_notif]
+                        .spacing(8)
+                        .align_y(Alignment::Center)
+                } else {
+                    row![app_name, close_notif]
+                        .spacing(8)
+                        .align_y(Alignment::Center)
+                },
+                body
+            )
+            .width(Length::Fill),
+        )
     }
 
     fn replace_notification(&mut self, notification: Notification) -> Task<Message> {
-        if let Some(notif) = self.cards.iter_mut().find(|n| n.id == notification.id) {
-            *notif = notification;
+        if let Some(pending) = self
+            .pending_notifications
+            .iter_mut()
+            .find(|n| n.id == notification.id)
+        {
+            *pending = notification;
             Task::none()
+        } else if self.store.is_active(notification.id) {
+            let timeout = expiry::timeout(&self.config, &notification);
+            let timer = self.timers.start(notification.id, timeout, Instant::now());
+            let effects = self.store.replace(notification);
+            Task::batch([schedule(timer), self.apply(effects)])
         } else {
-            tracing::error!("Notification not found... pushing instead");
+            // The notification expired or was never displayed.
             self.push_notification(notification)
         }
     }
@@ -651,13 +889,7 @@ impl CosmicNotifications {
         action: Option<ActionId>,
     ) -> Option<Task<Message>> {
         if let Some(tx) = self.notifications_tx.as_ref() {
-            let c_pos = self.cards.iter().position(|n| n.id == id);
-            let notification = c_pos.map(|c_pos| &self.cards[c_pos]).or_else(|| {
-                self.hidden
-                    .iter()
-                    .position(|n| n.id == id)
-                    .map(|pos| &self.hidden[pos])
-            })?;
+            let notification = self.store.get(id)?;
 
             let maybe_action = if action
                 .as_ref()
@@ -671,10 +903,19 @@ impl CosmicNotifications {
             {
                 Some(ActionId::Default.to_string())
             } else {
-                notification.actions.first().map(|a| a.0.to_string())
+                None
             };
 
             let Some(action) = maybe_action else {
+                // Without a default action, the application is brought forward instead.
+                if let Some(app_id) = notification.desktop_entry()
+                    && let Some(entry) = self.applications.find(app_id)
+                {
+                    let app_id = app_id.to_string();
+                    tokio::spawn(async move {
+                        desktop::launch(&app_id, &entry, Some(token)).await;
+                    });
+                }
                 return self.close(id, CloseReason::Dismissed);
             };
             let tx = tx.clone();
@@ -693,6 +934,43 @@ impl CosmicNotifications {
     }
 }
 
+/// Sends [`Message::Timeout`] once the timeout elapsed.
+fn schedule(timeout: Option<expiry::Timeout>) -> Task<Message> {
+    match timeout {
+        Some(timeout) => iced::Task::perform(tokio::time::sleep(timeout.after), move |_| {
+            cosmic::action::app(Message::Timeout(timeout))
+        }),
+        None => iced::Task::none(),
+    }
+}
+
+/// Opens a body hyperlink with the desktop's URI handler.
+fn open_link(token: Option<String>, url: &Url) {
+    // The file may have changed since the body was parsed.
+    if markup::file_link_launches(url) {
+        tracing::warn!("Refusing to open {url}: it would be run");
+        return;
+    }
+
+    let mut cmd = std::process::Command::new("xdg-open");
+    cmd.arg(url.as_str());
+    if let Some(token) = token {
+        cmd.env("XDG_ACTIVATION_TOKEN", &token);
+        cmd.env("DESKTOP_STARTUP_ID", &token);
+    } else {
+        tracing::warn!("Failed to get activation token for link.");
+    }
+
+    let url = url.clone();
+    tokio::task::spawn_blocking(move || match cmd.status() {
+        Ok(status) if !status.success() => {
+            tracing::error!("Failed to open {url}: xdg-open exited with {status}");
+        }
+        Err(err) => tracing::error!("Failed to open {url}: {err}"),
+        Ok(_) => {}
+    });
+}
+
 impl cosmic::Application for CosmicNotifications {
     type Message = Message;
     type Executor = cosmic::executor::single::Executor;
@@ -722,6 +1000,14 @@ impl cosmic::Application for CosmicNotifications {
                 })
             })
             .unwrap_or_default();
+        let dnd = dnd::state(&config, Local::now());
+        let history = History::new();
+        let hidden = history
+            .as_ref()
+            .map(|history| history.load(&config))
+            .unwrap_or_default();
+        let restored_ids = hidden.iter().map(|n| n.id).collect();
+        let store = NotificationStore::new(hidden, config.max_notifications, config.max_per_app);
         (
             CosmicNotifications {
                 core,
@@ -734,8 +1020,19 @@ impl cosmic::Application for CosmicNotifications {
                 notifications_id: id::Id::new("Notifications"),
                 notifications_tx: None,
                 pending_notifications: Vec::new(),
-                cards: Vec::with_capacity(50),
-                hidden: VecDeque::new(),
+                store,
+                timers: Timers::default(),
+                hovered_popup: None,
+                hovered: None,
+                reply: None,
+                reply_id: id::Id::new("Reply"),
+                applications: Applications::default(),
+                history,
+                restored_ids,
+                dnd,
+                fullscreen: false,
+                screencast_active: false,
+                sounds: Sounds::new(sound::default_backend()),
                 popups: Vec::with_capacity(3),
             },
             Task::none(),
@@ -761,6 +1058,21 @@ impl cosmic::Application for CosmicNotifications {
                 tracing::trace!("requesting token for {id}");
                 return self.request_activation(id, None);
             }
+            Message::InvokeAction(id, action) => {
+                tracing::trace!("requesting token for {id} {action}");
+                return self.request_activation(id, Some(action));
+            }
+            Message::OpenLink(url) => {
+                tracing::trace!("requesting token for {url}");
+                return activation::request_token(
+                    Some(String::from(Self::APP_ID)),
+                    Some(self.window_id),
+                )
+                .map(move |token| cosmic::Action::App(Message::OpenLinkToken(token, url.clone())));
+            }
+            Message::OpenLinkToken(token, url) => {
+                open_link(token, &url);
+            }
             Message::ActivationToken(token, id, action) => {
                 tracing::trace!("token for {id}");
                 if let Some(token) = token {
@@ -773,10 +1085,33 @@ impl cosmic::Application for CosmicNotifications {
             }
             Message::Notification(e) => match e {
                 notifications::Event::Notification(n) => {
-                    return self.push_notification(n);
+                    if self.held_by_dnd(&n) {
+                        return self.hide(n);
+                    } else {
+                        self.sounds.play(&self.config, &n);
+                        return self.push_notification(n);
+                    }
                 }
                 notifications::Event::Replace(n) => {
-                    return self.replace_notification(n);
+                    if !self.store.is_active(n.id) && self.held_by_dnd(&n) {
+                        return self.hide(n);
+                    } else {
+                        return self.replace_notification(n);
+                    }
+                }
+                notifications::Event::Hidden(n) => {
+                    return self.hide(n);
+                }
+                notifications::Event::BodyImages { id, body, images } => {
+                    // The images of a body which was replaced meanwhile are stale.
+                    if let Some(n) = self.store.get_mut(id)
+                        && n.body == body
+                    {
+                        n.body_images = images;
+                        if !self.store.is_active(id) {
+                            self.save_history();
+                        }
+                    }
                 }
                 notifications::Event::CloseNotification(id) => {
                     if let Some(c) = self.close(id, CloseReason::CloseNotification) {
@@ -784,37 +1119,207 @@ impl cosmic::Application for CosmicNotifications {
                     }
                 }
                 notifications::Event::Ready(tx) => {
+                    if self.dnd.is_active() {
+                        let dnd = self.dnd;
+                        let sender = tx.clone();
+                        tokio::spawn(async move {
+                            _ = sender.send(notifications::Input::DoNotDisturb(dnd)).await;
+                        });
+                    }
+                    if self.config.broadcast_signals {
+                        let sender = tx.clone();
+                        tokio::spawn(async move {
+                            _ = sender.send(notifications::Input::Broadcast(true)).await;
+                        });
+                    }
+                    let rate_limit = notifications::Input::RateLimit {
+                        burst: self.config.rate_limit_burst,
+                        per_minute: self.config.rate_limit_per_minute,
+                    };
+                    let rules = notifications::Input::Rules(self.config.rules.clone());
+                    let sender = tx.clone();
+                    tokio::spawn(async move {
+                        _ = sender.send(rate_limit).await;
+                        _ = sender.send(rules).await;
+                    });
                     self.notifications_tx = Some(tx);
                 }
                 notifications::Event::AppletActivated { id, action } => {
                     tracing::trace!("requesting token for {id}");
                     return self.request_activation(id, Some(action));
                 }
+                notifications::Event::History(request) => {
+                    return self.history_request(request);
+                }
             },
             Message::Dismissed(id) => {
                 if let Some(c) = self.close(id, CloseReason::Dismissed) {
                     return c;
                 }
             }
-            Message::Timeout(id) => {
-                let mut tasks = Vec::with_capacity(2);
-                tasks.push(self.expire(id));
-                if self.cards.is_empty() && self.active_surface {
-                    self.active_surface = false;
-                    tasks.push(destroy_layer_surface(self.window_id));
+            Message::Timeout(timeout) => {
+                if self.timers.expired(timeout) {
+                    let effects = self.store.expire(timeout.id);
+                    // The notification stays in the history, but is closed for
+                    // its client.
+                    if !effects.is_empty()
+                        && let Some(sender) = self.notifications_tx.clone()
+                    {
+                        let id = timeout.id;
+                        tokio::spawn(async move {
+                            _ = sender
+                                .send(notifications::Input::Closed(id, CloseReason::Expired))
+                                .await;
+                        });
+                    }
+                    return self.apply(effects);
+                }
+            }
+            Message::Hover(nth, true) => {
+                self.hovered_popup = Some(nth);
+                return self.sync_hover();
+            }
+            Message::Hover(nth, false) => {
+                // The pointer may enter the next popup before leaving this one.
+                if self.hovered_popup == Some(nth) {
+                    self.hovered_popup = None;
+                }
+                return self.sync_hover();
+            }
+            Message::Idle(idle) => {
+                return Task::batch(self.set_idle(idle));
+            }
+            Message::Screencast(active) => {
+                self.screencast_active = active;
+                self.update_dnd();
+            }
+            Message::StartReply(id) => {
+                // Another reply which is being typed is dropped.
+                self.reply = Some((id, String::new()));
+                self.timers.pause(id, Instant::now());
+                // The reply input is focused once the popups are recreated.
+                return Task::batch([
+                    set_keyboard_interactivity(self.window_id, KeyboardInteractivity::OnDemand),
+                    self.recreate_popups(),
+                ]);
+            }
+            Message::ReplyInput(input) => {
+                if let Some((_, text)) = &mut self.reply {
+                    *text = input;
+                }
+            }
+            Message::SendReply => {
+                if let Some((id, text)) = self.reply.clone()
+                    && !text.trim().is_empty()
+                {
+                    if let Some(sender) = self.notifications_tx.clone() {
+                        tokio::spawn(async move {
+                            _ = sender
+                                .send(notifications::Input::Replied { id, text })
+                                .await;
+                        });
+                    }
+                    // The conversation continues in the application.
+                    self.timers.cancel(id);
+                    let stop_reply = self.stop_reply();
+                    let effects = self.store.expire(id);
+                    return Task::batch([stop_reply, self.apply(effects)]);
+                }
+            }
+            Message::PopupClosed(id) => {
+                // Popups which grab are dismissed by the compositor when the
+                // user clicks elsewhere, which cancels the reply.
+                if self.reply.is_some() && self.popups.iter().any(|p| p.0 == id) {
+                    self.popups.retain(|p| p.0 != id);
+                    return self.update(Message::CancelReply);
+                }
+            }
+            Message::CancelReply => {
+                if let Some((id, _)) = self.reply {
+                    let stop_reply = self.stop_reply();
+                    if self.hovered == Some(id) {
+                        return stop_reply;
+                    }
+                    return Task::batch([
+                        stop_reply,
+                        schedule(self.timers.resume(id, Instant::now())),
+                    ]);
                 }
-                return Task::batch(tasks);
             }
             Message::Config(config) => {
+                let retention_changed = config.history_max_count != self.config.history_max_count
+                    || config.history_max_age != self.config.history_max_age;
+                if config.rules != self.config.rules
+                    && let Some(sender) = self.notifications_tx.clone()
+                {
+                    let rules = notifications::Input::Rules(config.rules.clone());
+                    tokio::spawn(async move {
+                        _ = sender.send(rules).await;
+                    });
+                }
+                if config.broadcast_signals != self.config.broadcast_signals
+                    && let Some(sender) = self.notifications_tx.clone()
+                {
+                    let broadcast = config.broadcast_signals;
+                    tokio::spawn(async move {
+                        _ = sender
+                            .send(notifications::Input::Broadcast(broadcast))
+                            .await;
+                    });
+                }
+                if (config.rate_limit_burst, config.rate_limit_per_minute)
+                    != (
+                        self.config.rate_limit_burst,
+                        self.config.rate_limit_per_minute,
+                    )
+                    && let Some(sender) = self.notifications_tx.clone()
+                {
+                    let rate_limit = notifications::Input::RateLimit {
+                        burst: config.rate_limit_burst,
+                        per_minute: config.rate_limit_per_minute,
+                    };
+                    tokio::spawn(async move {
+                        _ = sender.send(rate_limit).await;
+                    });
+                }
+                let effects = self
+                    .store
+                    .set_limits(config.max_notifications, config.max_per_app);
+                // The watchers stop with their setting, and start over when it
+                // is turned on again.
+                if !config.do_not_disturb_fullscreen {
+                    self.fullscreen = false;
+                }
+                if !config.do_not_disturb_screencast {
+                    self.screencast_active = false;
+                }
                 self.config = config;
+                if retention_changed {
+                    self.save_history();
+                }
+                self.update_dnd();
+                let mut tasks = vec![self.apply(effects)];
+                if self.config.idle_threshold.is_none() {
+                    tasks.extend(self.set_idle(false));
+                }
+                return Task::batch(tasks);
             }
             Message::PanelConfig(c) => {
                 self.panel_config = c;
                 self.anchor = Some(self.anchor_for_notification_applet());
+                self.update_dnd();
             }
             Message::DockConfig(c) => {
                 self.dock_config = c;
                 self.anchor = Some(self.anchor_for_notification_applet());
+                self.update_dnd();
+            }
+            Message::DndTick => {
+                self.update_dnd();
+            }
+            Message::Fullscreen(fullscreen) => {
+                self.fullscreen = fullscreen;
+                self.update_dnd();
             }
             Message::Ignore => {}
             Message::Surface(a) => {
@@ -850,6 +1355,10 @@ impl cosmic::Application for CosmicNotifications {
                 for pending_notif in pending.into_iter().rev() {
                     tasks.push(self.push_notification(pending_notif));
                 }
+                tasks.push(self.reposition(self.store.visible()));
+                if self.reply.is_some() {
+                    tasks.push(widget::text_input::focus(self.reply_id.clone()));
+                }
 
                 return Task::batch(tasks);
             }
@@ -868,7 +1377,7 @@ impl cosmic::Application for CosmicNotifications {
     }
 
     fn subscription(&self) -> Subscription<Message> {
-        Subscription::batch(vec![
+        let mut subscriptions = vec![
             listen_raw(|e, _, id| match e {
                 cosmic::iced::Event::Window(iced::window::Event::Opened { position: _, size }) => {
                     Some(Message::PopupSize(id, size))
@@ -876,6 +1385,13 @@ impl cosmic::Application for CosmicNotifications {
                 cosmic::iced::Event::Window(iced::window::Event::Resized(s)) => {
                     Some(Message::PopupSize(id, s))
                 }
+                cosmic::iced::Event::Window(iced::window::Event::Closed) => {
+                    Some(Message::PopupClosed(id))
+                }
+                cosmic::iced::Event::Keyboard(iced::keyboard::Event::KeyPressed {
+                    key: iced::keyboard::Key::Named(iced::keyboard::key::Named::Escape),
+                    ..
+                }) => Some(Message::CancelReply),
                 _ => None,
             }),
             self.core
@@ -914,7 +1430,18 @@ impl cosmic::Application for CosmicNotifications {
                     }
                     Message::DockConfig(u.config)
                 }),
-            notifications::notifications().map(Message::Notification),
-        ])
+            notifications::notifications(self.restored_ids.clone()).map(Message::Notification),
+            iced::time::every(Duration::from_secs(15)).map(|_| Message::DndTick),
+        ];
+        if self.config.do_not_disturb_fullscreen {
+            subscriptions.push(toplevels::fullscreen().map(Message::Fullscreen));
+        }
+        if let Some(threshold) = self.config.idle_threshold {
+            subscriptions.push(idle::idle(threshold).map(Message::Idle));
+        }
+        if self.config.do_not_disturb_screencast {
+            subscriptions.push(screencast::screencast().map(Message::Screencast));
+        }
+        Subscription::batch(subscriptions)
     }
 }
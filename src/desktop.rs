@@ -0,0 +1,320 @@
+use cosmic_notifications_util::{Hint, Notification};
+use std::{collections::HashMap, fs, path::PathBuf, sync::Mutex};
+use tokio::{process::Command, sync::OnceCell};
+use zbus::{Connection, zvariant::Value};
+
+/// The XDG data directories, the most important first.
+pub fn data_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    dirs.extend(dirs::data_dir());
+    let data_dirs = std::env::var_os("XDG_DATA_DIRS")
+        .filter(|d| !d.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".into());
+    dirs.extend(std::env::split_paths(&data_dirs));
+    dirs
+}
+
+/// What a desktop file says about its application.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DesktopEntry {
+    pub name: String,
+    pub icon: Option<String>,
+    pub exec: Option<String>,
+    /// Whether the application can be activated with the
+    /// `org.freedesktop.Application` interface.
+    pub dbus_activatable: bool,
+}
+
+/// Finds the desktop files of applications by their id.
+#[derive(Debug)]
+pub struct Applications {
+    dirs: Vec<PathBuf>,
+    /// Keys of localized names to look up, the most specific first.
+    locales: Vec<String>,
+    /// Entries which were found. Missing ones are looked up again, as the
+    /// application may have been installed since.
+    cache: Mutex<HashMap<String, DesktopEntry>>,
+}
+
+impl Default for Applications {
+    fn default() -> Self {
+        let mut dirs: Vec<PathBuf> = data_dirs()
+            .into_iter()
+            .map(|d| d.join("applications"))
+            .collect();
+        // Exported by Flatpak and snapd, in case the session does not list them.
+        dirs.extend(dirs::data_dir().map(|d| d.join("flatpak/exports/share/applications")));
+        dirs.push(PathBuf::from("/var/lib/flatpak/exports/share/applications"));
+        dirs.push(PathBuf::from("/var/lib/snapd/desktop/applications"));
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .find_map(|var| std::env::var(var).ok().filter(|l| !l.is_empty()))
+            .unwrap_or_default();
+        Self::with_dirs(dirs).with_locale(&locale)
+    }
+}
+
+impl Applications {
+    /// Searches the `dirs` for desktop files, the most important first.
+    pub fn with_dirs(dirs: Vec<PathBuf>) -> Self {
+        Self {
+            dirs,
+            locales: Vec::new(),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Prefers names translated for the POSIX `locale`, like `de_DE.UTF-8`.
+    pub fn with_locale(mut self, locale: &str) -> Self {
+        self.locales = locale_keys(locale);
+        self
+    }
+
+    pub fn find(&self, app_id: &str) -> Option<DesktopEntry> {
+        if app_id.is_empty() || app_id.contains('/') || app_id.starts_with('.') {
+            return None;
+        }
+        if let Some(entry) = self.cache.lock().unwrap().get(app_id) {
+            return Some(entry.clone());
+        }
+
+        let file = format!("{app_id}.desktop");
+        let entry = self
+            .dirs
+            .iter()
+            .find_map(|dir| fs::read_to_string(dir.join(&file)).ok())
+            .and_then(|contents| parse(&contents, &self.locales))?;
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(app_id.to_string(), entry.clone());
+        Some(entry)
+    }
+
+    /// Displays `n` with the name of the application its `desktop-entry`
+    /// hint refers to, and with its icon if it has none of its own. The
+    /// reported `app_name` is kept for rules and Do Not Disturb.
+    pub fn resolve(&self, n: &mut Notification) {
+        let Some(entry) = n.desktop_entry().and_then(|id| {
+            // Some clients include the extension, which the hint should not have.
+            self.find(id.strip_suffix(".desktop").unwrap_or(id))
+        }) else {
+            return;
+        };
+
+        n.display_name = Some(entry.name);
+        if n.app_icon.is_empty() {
+            n.display_icon = entry.icon;
+        }
+    }
+
+    /// Displays `n` with the identity of the application `app_id`, which was
+    /// verified by the server, instead of the self-reported one.
+    pub fn identify(&self, n: &mut Notification, app_id: &str) {
+        n.hints.retain(|h| !matches!(h, Hint::DesktopEntry(_)));
+        n.hints.push(Hint::DesktopEntry(app_id.to_string()));
+
+        match self.find(app_id) {
+            Some(entry) => {
+                n.display_name = Some(entry.name);
+                n.display_icon = Some(entry.icon.unwrap_or_default());
+            }
+            None => {
+                tracing::warn!("No desktop file for sandboxed application {app_id}");
+                n.display_name = Some(app_id.to_string());
+                n.display_icon = Some(String::new());
+            }
+        }
+    }
+}
+
+/// The `Name` and `Icon` keys of the `[Desktop Entry]` group, with the name
+/// translated for the first of the `locales` it has a translation for.
+fn parse(contents: &str, locales: &[String]) -> Option<DesktopEntry> {
+    let mut in_entry = false;
+    let mut name = None;
+    let mut translations = HashMap::new();
+    let mut icon = None;
+    let mut exec = None;
+    let mut dbus_activatable = false;
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_entry = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_entry {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        match key.trim() {
+            "Name" => name = Some(value.trim().to_string()),
+            "Icon" => icon = Some(value.trim().to_string()).filter(|i| !i.is_empty()),
+            "Exec" => exec = Some(value.trim().to_string()).filter(|e| !e.is_empty()),
+            "DBusActivatable" => dbus_activatable = value.trim() == "true",
+            key => {
+                if let Some(locale) = key
+                    .strip_prefix("Name[")
+                    .and_then(|key| key.strip_suffix(']'))
+                {
+                    translations.insert(locale, value.trim());
+                }
+            }
+        }
+    }
+
+    let name = locales
+        .iter()
+        .find_map(|locale| translations.get(locale.as_str()))
+        .map(|name| name.to_string())
+        .or(name)?;
+    Some(DesktopEntry {
+        name,
+        icon,
+        exec,
+        dbus_activatable,
+    })
+}
+
+/// Brings the application `app_id` forward, with D-Bus activation if it
+/// supports it or by running its `Exec` command otherwise.
+pub async fn launch(app_id: &str, entry: &DesktopEntry, token: Option<String>) {
+    if entry.dbus_activatable {
+        match activate(app_id, token.as_deref()).await {
+            Ok(()) => return,
+            Err(err) => tracing::warn!("Failed to activate {app_id}: {err}"),
+        }
+    }
+
+    let Some((program, args)) = entry
+        .exec
+        .as_deref()
+        .map(exec_args)
+        .and_then(|args| Some((args.first()?.clone(), args[1..].to_vec())))
+    else {
+        tracing::warn!("Failed to launch {app_id}: the desktop file has no Exec key");
+        return;
+    };
+    let mut cmd = Command::new(program);
+    cmd.args(args);
+    if let Some(token) = token {
+        cmd.env("XDG_ACTIVATION_TOKEN", &token);
+        cmd.env("DESKTOP_STARTUP_ID", &token);
+    }
+
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(err) => {
+            tracing::error!("Failed to launch {app_id}: {err}");
+            return;
+        }
+    };
+
+    // The application outlives the click, so it is only reaped when it exits.
+    let app_id = app_id.to_string();
+    tokio::spawn(async move {
+        match child.wait().await {
+            Ok(status) if !status.success() => {
+                tracing::warn!("{app_id} exited with {status}");
+            }
+            Err(err) => tracing::warn!("Failed to wait for {app_id}: {err}"),
+            Ok(_) => {}
+        }
+    });
+}
+
+/// Calls `org.freedesktop.Application.Activate` on the well-known name `app_id`.
+async fn activate(app_id: &str, token: Option<&str>) -> zbus::Result<()> {
+    static SESSION: OnceCell<Connection> = OnceCell::const_new();
+    let conn = SESSION.get_or_try_init(Connection::session).await?;
+    let path = format!("/{}", app_id.replace('.', "/").replace('-', "_"));
+    let mut platform_data = HashMap::new();
+    if let Some(token) = token {
+        platform_data.insert("activation-token", Value::from(token));
+        platform_data.insert("desktop-startup-id", Value::from(token));
+    }
+
+    conn.call_method(
+        Some(app_id),
+        path.as_str(),
+        Some("org.freedesktop.Application"),
+        "Activate",
+        &(platform_data,),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Splits an `Exec` value into the program and its arguments, leaving out
+/// the field codes for files and URLs, as nothing is opened with it.
+pub fn exec_args(exec: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut arg = String::new();
+    let mut in_arg = false;
+    let mut quoted = false;
+    let mut chars = exec.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_arg = true;
+            }
+            '\\' if quoted => arg.extend(chars.next()),
+            c if c.is_whitespace() && !quoted => {
+                if in_arg {
+                    args.push(std::mem::take(&mut arg));
+                    in_arg = false;
+                }
+            }
+            c => {
+                arg.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        args.push(arg);
+    }
+
+    args.into_iter()
+        .filter_map(|arg| match arg.as_str() {
+            "%f" | "%F" | "%u" | "%U" | "%d" | "%D" | "%n" | "%N" | "%i" | "%c" | "%k" | "%v"
+            | "%m" => None,
+            // Flatpak forwards files and URLs between these.
+            "@@" | "@@f" | "@@u" => None,
+            _ => Some(arg.replace("%%", "%")),
+        })
+        .collect()
+}
+
+/// The keys of localized values matching `locale`, the most specific first,
+/// as in `lang_COUNTRY@MODIFIER`, `lang_COUNTRY`, `lang@MODIFIER`, `lang`.
+fn locale_keys(locale: &str) -> Vec<String> {
+    let (locale, modifier) = match locale.split_once('@') {
+        Some((locale, modifier)) => (locale, Some(modifier)),
+        None => (locale, None),
+    };
+    // The encoding is not part of the keys.
+    let locale = locale.split('.').next().unwrap_or_default();
+    let (lang, country) = match locale.split_once('_') {
+        Some((lang, country)) => (lang, Some(country)),
+        None => (locale, None),
+    };
+    if lang.is_empty() || lang == "C" || lang == "POSIX" {
+        return Vec::new();
+    }
+
+    let mut keys = Vec::with_capacity(4);
+    if let (Some(country), Some(modifier)) = (country, modifier) {
+        keys.push(format!("{lang}_{country}@{modifier}"));
+    }
+    if let Some(country) = country {
+        keys.push(format!("{lang}_{country}"));
+    }
+    if let Some(modifier) = modifier {
+        keys.push(format!("{lang}@{modifier}"));
+    }
+    keys.push(lang.to_string());
+    keys
+}
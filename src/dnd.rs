@@ -0,0 +1,88 @@
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, NaiveTime, TimeDelta};
+use cosmic_notifications_config::{DndSchedule, NotificationsConfig, Weekday};
+use cosmic_notifications_util::Notification;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Dnd {
+    #[default]
+    Off,
+    /// Do Not Disturb is active, until the given time if it is not indefinite.
+    On { until: Option<SystemTime> },
+}
+
+impl Dnd {
+    pub fn is_active(self) -> bool {
+        matches!(self, Dnd::On { .. })
+    }
+}
+
+/// Evaluates whether Do Not Disturb is active at `now`.
+pub fn state(config: &NotificationsConfig, now: DateTime<Local>) -> Dnd {
+    if config.do_not_disturb {
+        return Dnd::On { until: None };
+    }
+
+    let timed = config
+        .do_not_disturb_until
+        .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+        .filter(|until| *until > SystemTime::from(now));
+
+    let scheduled = config
+        .do_not_disturb_schedules
+        .iter()
+        .filter_map(|schedule| schedule_end(schedule, now))
+        .max()
+        .map(SystemTime::from);
+
+    match timed.max(scheduled) {
+        Some(until) => Dnd::On { until: Some(until) },
+        None => Dnd::Off,
+    }
+}
+
+/// Whether the notification is shown even while Do Not Disturb is active.
+pub fn allowed(config: &NotificationsConfig, n: &Notification) -> bool {
+    let allow = &config.do_not_disturb_allow;
+    (allow.critical && n.urgency() == 2)
+        || allow
+            .apps
+            .iter()
+            .any(|app| *app == n.app_name || n.desktop_entry() == Some(app.as_str()))
+        || allow.categories.iter().any(|c| n.category_matches(c))
+}
+
+/// The end of the window of `schedule` containing `now`, if any.
+fn schedule_end(schedule: &DndSchedule, now: DateTime<Local>) -> Option<DateTime<Local>> {
+    let today = now.date_naive();
+    // A window which started yesterday may still be active.
+    [today.checked_sub_days(Days::new(1))?, today]
+        .into_iter()
+        .filter(|day| schedule.days.is_empty() || schedule.days.contains(&weekday(*day)))
+        .filter_map(|day| {
+            let start = at_minutes(day, schedule.start)?;
+            let mut end = at_minutes(day, schedule.end)?;
+            if end <= start {
+                end += TimeDelta::days(1);
+            }
+            (start..end).contains(&now).then_some(end)
+        })
+        .max()
+}
+
+fn at_minutes(day: NaiveDate, minutes: u16) -> Option<DateTime<Local>> {
+    let time = NaiveTime::from_hms_opt(u32::from(minutes / 60), u32::from(minutes % 60), 0)?;
+    day.and_time(time).and_local_timezone(Local).earliest()
+}
+
+fn weekday(day: NaiveDate) -> Weekday {
+    match day.weekday() {
+        chrono::Weekday::Mon => Weekday::Monday,
+        chrono::Weekday::Tue => Weekday::Tuesday,
+        chrono::Weekday::Wed => Weekday::Wednesday,
+        chrono::Weekday::Thu => Weekday::Thursday,
+        chrono::Weekday::Fri => Weekday::Friday,
+        chrono::Weekday::Sat => Weekday::Saturday,
+        chrono::Weekday::Sun => Weekday::Sunday,
+    }
+}
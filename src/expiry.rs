@@ -0,0 +1,144 @@
+use cosmic_notifications_config::NotificationsConfig;
+use cosmic_notifications_util::Notification;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// How long a notification is displayed before it expires, or `None` if it
+/// is displayed until it is dismissed.
+pub fn timeout(config: &NotificationsConfig, n: &Notification) -> Option<Duration> {
+    let (default, max) = match n.urgency() {
+        2 => (config.default_timeout_urgent, config.max_timeout_urgent),
+        1 => (config.default_timeout_normal, config.max_timeout_normal),
+        _ => (config.default_timeout_low, config.max_timeout_low),
+    };
+
+    let timeout = match n.expire_timeout {
+        // The sender leaves the timeout to the server.
+        ..0 => default?,
+        0 if config.expire_persistent => max.or(default)?,
+        0 => return None,
+        timeout => timeout.unsigned_abs(),
+    };
+    let timeout = max.map_or(timeout, |max| timeout.min(max));
+    (timeout > 0).then(|| Duration::from_millis(u64::from(timeout)))
+}
+
+/// How long a paused timer keeps running in addition to its remaining time
+/// once it is resumed.
+pub const RESUME_GRACE: Duration = Duration::from_secs(1);
+
+/// An expiry scheduled by [`Timers`], which is ignored if the timer of the
+/// notification was restarted, paused or cancelled in the meantime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeout {
+    pub id: u32,
+    pub after: Duration,
+    generation: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Timer {
+    Running { deadline: Instant, generation: u64 },
+    Paused { remaining: Duration },
+}
+
+/// Expiry timers of the displayed notifications.
+#[derive(Debug, Default)]
+pub struct Timers {
+    timers: HashMap<u32, Timer>,
+    generation: u64,
+    /// All timers are paused while the user is away.
+    idle: bool,
+}
+
+impl Timers {
+    /// Starts the timer of a notification, replacing its previous timer. A
+    /// paused timer stays paused, with the new timeout remaining, and timers
+    /// started while the user is away are paused.
+    pub fn start(&mut self, id: u32, timeout: Option<Duration>, now: Instant) -> Option<Timeout> {
+        let Some(timeout) = timeout else {
+            self.timers.remove(&id);
+            return None;
+        };
+        if self.idle || matches!(self.timers.get(&id), Some(Timer::Paused { .. })) {
+            self.timers.insert(id, Timer::Paused { remaining: timeout });
+            return None;
+        }
+        Some(self.run(id, timeout, now))
+    }
+
+    pub fn cancel(&mut self, id: u32) {
+        self.timers.remove(&id);
+    }
+
+    pub fn pause(&mut self, id: u32, now: Instant) {
+        if let Some(timer) = self.timers.get_mut(&id)
+            && let Timer::Running { deadline, .. } = *timer
+        {
+            *timer = Timer::Paused {
+                remaining: deadline.saturating_duration_since(now),
+            };
+        }
+    }
+
+    pub fn resume(&mut self, id: u32, now: Instant) -> Option<Timeout> {
+        if self.idle {
+            return None;
+        }
+        let Some(Timer::Paused { remaining }) = self.timers.get(&id).copied() else {
+            return None;
+        };
+        Some(self.run(id, remaining + RESUME_GRACE, now))
+    }
+
+    /// Pauses all timers while the user is away, and resumes them once they
+    /// are back, except the timer of the `hovered` notification.
+    pub fn set_idle(&mut self, idle: bool, now: Instant, hovered: Option<u32>) -> Vec<Timeout> {
+        if idle == self.idle {
+            return Vec::new();
+        }
+
+        let ids: Vec<u32> = self.timers.keys().copied().collect();
+        if idle {
+            for id in ids {
+                self.pause(id, now);
+            }
+            self.idle = true;
+            Vec::new()
+        } else {
+            self.idle = false;
+            ids.into_iter()
+                .filter(|id| Some(*id) != hovered)
+                .filter_map(|id| self.resume(id, now))
+                .collect()
+        }
+    }
+
+    /// Whether the timeout belongs to the running timer of its notification,
+    /// which is then removed.
+    pub fn expired(&mut self, timeout: Timeout) -> bool {
+        match self.timers.get(&timeout.id) {
+            Some(Timer::Running { generation, .. }) if *generation == timeout.generation => {
+                self.timers.remove(&timeout.id);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn run(&mut self, id: u32, after: Duration, now: Instant) -> Timeout {
+        self.generation += 1;
+        self.timers.insert(
+            id,
+            Timer::Running {
+                deadline: now + after,
+                generation: self.generation,
+            },
+        );
+        Timeout {
+            id,
+            after,
+            generation: self.generation,
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use cosmic_notifications_config::NotificationsConfig;
+use cosmic_notifications_util::{Hint, Image, Notification, write_png};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    hash::{DefaultHasher, Hash, Hasher},
+    io::Write,
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, SystemTime},
+};
+use tokio::task::JoinHandle;
+
+const HISTORY_FILE: &str = "history.json";
+const IMAGES_DIR: &str = "images";
+
+/// Stored image files by notification id and time, and position of the image.
+type ImageFiles = HashMap<(u32, SystemTime, usize), PathBuf>;
+
+/// On-disk store for notifications that are no longer displayed.
+///
+/// The history is kept as JSON under `$XDG_STATE_HOME/cosmic-notifications`,
+/// with image data written to separate PNG files so the history stays small.
+/// Images which are no longer referenced are cleaned up after every save.
+#[derive(Debug, Clone)]
+pub struct History {
+    dir: PathBuf,
+    write_lock: Arc<Mutex<()>>,
+    /// Incremented by every save, so that snapshots which were superseded
+    /// before they could be written are skipped.
+    generation: Arc<AtomicU64>,
+    /// The files of images which were already stored, so that they are not
+    /// hashed again on every save.
+    files: Arc<Mutex<ImageFiles>>,
+}
+
+impl History {
+    pub fn new() -> Option<Self> {
+        let Some(state_dir) = dirs::state_dir() else {
+            tracing::warn!("Failed to find the state directory, history will not be saved");
+            return None;
+        };
+
+        Some(Self::with_dir(state_dir.join("cosmic-notifications")))
+    }
+
+    /// A history stored in `dir` instead of the state directory.
+    pub fn with_dir(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            write_lock: Arc::new(Mutex::new(())),
+            generation: Arc::new(AtomicU64::new(0)),
+            files: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn images_dir(&self) -> PathBuf {
+        self.dir.join(IMAGES_DIR)
+    }
+
+    /// Loads the saved history, newest first, and removes images which are no
+    /// longer referenced by it.
+    pub fn load(&self, config: &NotificationsConfig) -> VecDeque<Notification> {
+        let path = self.dir.join(HISTORY_FILE);
+        let mut history = match std::fs::read(&path) {
+            Ok(data) => match serde_json::from_slice(&data) {
+                Ok(history) => history,
+                Err(err) => {
+                    tracing::error!("Failed to parse history {}: {}", path.display(), err);
+                    VecDeque::new()
+                }
+            },
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => VecDeque::new(),
+            Err(err) => {
+                tracing::error!("Failed to read history {}: {}", path.display(), err);
+                VecDeque::new()
+            }
+        };
+
+        retain(&mut history, config);
+        self.remove_unused_images(&history);
+        history
+    }
+
+    /// Moves the image data of a notification into files in the history
+    /// directory, unless they are in `files` already.
+    fn store_images(&self, notification: &mut Notification, files: &mut ImageFiles) {
+        let images_dir = self.images_dir();
+        let (id, time) = (notification.id, notification.time);
+        let images = notification
+            .hints
+            .iter_mut()
+            .filter_map(|h| match h {
+                Hint::Image(image) => Some(image),
+                _ => None,
+            })
+            .chain(
+                notification
+                    .body_images
+                    .iter_mut()
+                    .filter_map(|i| i.image.as_mut()),
+            );
+
+        for (nth, image) in images.enumerate() {
+            if let Some(path) = files.get(&(id, time, nth)) {
+                *image = Image::File(path.clone());
+                continue;
+            }
+            let Image::Data {
+                width,
+                height,
+                data,
+            } = image
+            else {
+                continue;
+            };
+
+            let mut hasher = DefaultHasher::new();
+            (*width, *height, data.as_slice()).hash(&mut hasher);
+            let path = images_dir.join(format!("{:016x}.png", hasher.finish()));
+
+            if !path.exists() {
+                if let Err(err) = std::fs::create_dir_all(&images_dir) {
+                    tracing::error!("Failed to create {}: {}", images_dir.display(), err);
+                    return;
+                }
+                if let Err(err) = write_png(&path, *width, *height, data) {
+                    tracing::error!("Failed to save image {}: {}", path.display(), err);
+                    continue;
+                }
+            }
+
+            files.insert((id, time, nth), path.clone());
+            *image = Image::File(path);
+        }
+    }
+
+    /// Writes the history to disk in the background, with image data moved
+    /// into files, and removes the images it no longer references. Transient
+    /// notifications are not written.
+    pub fn save(&self, history: &VecDeque<Notification>) -> JoinHandle<()> {
+        let mut history: Vec<Notification> =
+            history.iter().filter(|n| !n.transient()).cloned().collect();
+
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let this = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let _guard = this.write_lock.lock().unwrap();
+            if this.generation.load(Ordering::SeqCst) != generation {
+                return;
+            }
+            let mut files = this.files.lock().unwrap();
+            for notification in &mut history {
+                this.store_images(notification, &mut files);
+            }
+            let saved: HashSet<_> = history.iter().map(|n| (n.id, n.time)).collect();
+            files.retain(|(id, time, _), _| saved.contains(&(*id, *time)));
+            drop(files);
+
+            let data = match serde_json::to_vec(&history) {
+                Ok(data) => data,
+                Err(err) => {
+                    tracing::error!("Failed to serialize history: {}", err);
+                    return;
+                }
+            };
+            match this.write(&data) {
+                Ok(()) => this.remove_unused_images(&history),
+                Err(err) => tracing::error!("Failed to save history: {}", err),
+            }
+        })
+    }
+
+    fn write(&self, data: &[u8]) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let tmp = self.dir.join(format!("{HISTORY_FILE}.tmp"));
+        let mut file = std::fs::File::create(&tmp)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp, self.dir.join(HISTORY_FILE))
+    }
+
+    fn remove_unused_images<'a>(&self, history: impl IntoIterator<Item = &'a Notification>) {
+        let Ok(entries) = std::fs::read_dir(self.images_dir()) else {
+            return;
+        };
+
+        let used: HashSet<&PathBuf> = history
+            .into_iter()
+            .flat_map(|n| {
+                n.hints
+                    .iter()
+                    .filter_map(|h| match h {
+                        Hint::Image(image) => Some(image),
+                        _ => None,
+                    })
+                    .chain(n.body_images.iter().filter_map(|i| i.image.as_ref()))
+            })
+            .filter_map(|image| match image {
+                Image::File(path) => Some(path),
+                _ => None,
+            })
+            .collect();
+
+        for path in entries.filter_map(|e| Some(e.ok()?.path())) {
+            if !used.contains(&path)
+                && let Err(err) = std::fs::remove_file(&path)
+            {
+                tracing::warn!("Failed to remove image {}: {}", path.display(), err);
+            }
+        }
+    }
+}
+
+/// Drops notifications that exceed the configured history count or age,
+/// returning their ids.
+pub fn retain(history: &mut VecDeque<Notification>, config: &NotificationsConfig) -> Vec<u32> {
+    let mut removed = Vec::new();
+    if let Some(max_age) = config.history_max_age.map(Duration::from_secs) {
+        history.retain(|n| {
+            let keep = n.duration_since().is_none_or(|age| age <= max_age);
+            if !keep {
+                removed.push(n.id);
+            }
+            keep
+        });
+    }
+    let max_count = config.history_max_count as usize;
+    if history.len() > max_count {
+        removed.extend(history.drain(max_count..).map(|n| n.id));
+    }
+    removed
+}
@@ -0,0 +1,10 @@
+pub mod config;
+pub mod desktop;
+pub mod dnd;
+pub mod expiry;
+pub mod history;
+pub mod provenance;
+pub mod rate_limit;
+pub mod rules;
+pub mod sound;
+pub mod subscriptions;
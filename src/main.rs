@@ -1,13 +1,9 @@
 mod app;
-mod config;
-mod subscriptions;
 
-use config::APP_ID;
+use cosmic_notifications::config::{self, APP_ID, VERSION};
 use tracing::{info, metadata::LevelFilter};
 use tracing_subscriber::{EnvFilter, fmt, prelude::*};
 
-use crate::config::VERSION;
-
 fn main() -> anyhow::Result<()> {
     color_backtrace::install();
     let trace = tracing_subscriber::registry();
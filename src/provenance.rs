@@ -0,0 +1,77 @@
+use cosmic_notifications_util::Provenance;
+use std::{fs, path::Path};
+use zbus::{Connection, fdo::DBusProxy, names::BusName, names::OwnedUniqueName};
+
+pub const PROC: &str = "/proc";
+
+/// Finds the process behind the unique name `sender` with the credentials
+/// the message bus knows about it.
+pub async fn resolve(conn: &Connection, sender: &OwnedUniqueName) -> Option<Provenance> {
+    let dbus = DBusProxy::new(conn).await.ok()?;
+    let credentials = match dbus
+        .get_connection_credentials(BusName::Unique(sender.inner().clone()))
+        .await
+    {
+        Ok(credentials) => credentials,
+        Err(err) => {
+            tracing::warn!("Failed to get the credentials of {sender}: {err}");
+            return None;
+        }
+    };
+
+    let uid = credentials.unix_user_id();
+    Some(match credentials.process_id() {
+        Some(pid) => from_proc(Path::new(PROC), pid, uid),
+        None => Provenance {
+            uid,
+            ..Provenance::default()
+        },
+    })
+}
+
+/// Reads the executable, cgroup, Flatpak app id and AppArmor label of `pid`
+/// from the proc filesystem mounted at `proc`.
+pub fn from_proc(proc: &Path, pid: u32, uid: Option<u32>) -> Provenance {
+    let dir = proc.join(pid.to_string());
+
+    Provenance {
+        pid: Some(pid),
+        uid,
+        exe: fs::read_link(dir.join("exe")).ok(),
+        cgroup: fs::read_to_string(dir.join("cgroup"))
+            .ok()
+            .and_then(|cgroup| parse_cgroup(&cgroup)),
+        flatpak_id: fs::read_to_string(dir.join("root/.flatpak-info"))
+            .ok()
+            .and_then(|info| parse_flatpak_info(&info)),
+        apparmor_label: fs::read_to_string(dir.join("attr/apparmor/current"))
+            .or_else(|_| fs::read_to_string(dir.join("attr/current")))
+            .ok()
+            .map(|label| label.trim_end_matches(['\n', '\0']).to_string())
+            .filter(|label| !label.is_empty()),
+    }
+}
+
+/// The unified cgroup hierarchy, or the first hierarchy on hybrid systems.
+fn parse_cgroup(cgroup: &str) -> Option<String> {
+    let path = |line: &str| line.splitn(3, ':').nth(2).map(str::to_string);
+    cgroup
+        .lines()
+        .find(|line| line.starts_with("0::"))
+        .or_else(|| cgroup.lines().next())
+        .and_then(path)
+        .filter(|path| !path.is_empty())
+}
+
+/// The `name` key of the `[Application]` group of a `.flatpak-info` file.
+fn parse_flatpak_info(info: &str) -> Option<String> {
+    let mut in_application = false;
+    for line in info.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_application = line == "[Application]";
+        } else if in_application && let Some(name) = line.strip_prefix("name=") {
+            return Some(name.trim().to_string()).filter(|name| !name.is_empty());
+        }
+    }
+    None
+}
@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Instant;
+
+/// Buckets are pruned once there are more than this many.
+const MAX_BUCKETS: usize = 256;
+
+/// A sender may notify for several applications, such as a portal does, so
+/// its own bucket is this many times larger than the bucket of each one.
+const SENDER_APPS: u32 = 4;
+
+/// Notifications which were dropped in a row because their sender exceeded
+/// its rate.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Throttled {
+    pub count: u32,
+    /// The id of the card summarizing the dropped notifications, once it
+    /// was created.
+    pub summary: Option<u32>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    throttled: Throttled,
+}
+
+impl Bucket {
+    fn new(burst: u32, now: Instant) -> Self {
+        Self {
+            tokens: f64::from(burst),
+            updated: now,
+            throttled: Throttled::default(),
+        }
+    }
+}
+
+/// Limits the rate of notifications with a token bucket for each sender and
+/// application name, and a larger one for each sender so that it cannot get
+/// a fresh burst by changing the application name.
+#[derive(Debug)]
+pub struct RateLimiter {
+    burst: u32,
+    per_minute: u32,
+    senders: HashMap<Option<String>, Bucket>,
+    buckets: HashMap<(Option<String>, String), Bucket>,
+}
+
+impl RateLimiter {
+    /// Allows `burst` notifications at once, refilled with `per_minute`
+    /// notifications per minute. A `burst` of 0 disables the limit.
+    pub fn new(burst: u32, per_minute: u32) -> Self {
+        Self {
+            burst,
+            per_minute,
+            senders: HashMap::new(),
+            buckets: HashMap::new(),
+        }
+    }
+
+    pub fn set_limits(&mut self, burst: u32, per_minute: u32) {
+        self.burst = burst;
+        self.per_minute = per_minute;
+    }
+
+    /// Takes a token for a notification of `app_name` from `sender`, or
+    /// counts it as throttled if there is none left.
+    pub fn check(
+        &mut self,
+        sender: Option<&str>,
+        app_name: &str,
+        now: Instant,
+    ) -> Result<(), Throttled> {
+        if self.burst == 0 {
+            return Ok(());
+        }
+
+        let (burst, per_minute) = (self.burst, self.per_minute);
+        let (sender_burst, sender_per_minute) = (
+            burst.saturating_mul(SENDER_APPS),
+            per_minute.saturating_mul(SENDER_APPS),
+        );
+        prune(&mut self.senders, sender_burst, sender_per_minute, now);
+        prune(&mut self.buckets, burst, per_minute, now);
+
+        let sender = sender.map(str::to_string);
+        let sender_bucket = self
+            .senders
+            .entry(sender.clone())
+            .or_insert_with(|| Bucket::new(sender_burst, now));
+        refill(sender_bucket, sender_burst, sender_per_minute, now);
+        if sender_bucket.tokens < 1.0 {
+            sender_bucket.throttled.count += 1;
+            return Err(sender_bucket.throttled);
+        }
+        sender_bucket.throttled = Throttled::default();
+
+        let bucket = self
+            .buckets
+            .entry((sender, app_name.to_string()))
+            .or_insert_with(|| Bucket::new(burst, now));
+        refill(bucket, burst, per_minute, now);
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            bucket.throttled = Throttled::default();
+            sender_bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            bucket.throttled.count += 1;
+            Err(bucket.throttled)
+        }
+    }
+
+    /// Records the card summarizing the notifications being throttled.
+    pub fn set_summary(&mut self, sender: Option<&str>, app_name: &str, id: u32) {
+        let sender = sender.map(str::to_string);
+        // Only the bucket which throttled the last notification counts it.
+        let bucket = match self.senders.get_mut(&sender) {
+            Some(bucket) if bucket.throttled.count > 0 => Some(bucket),
+            _ => self.buckets.get_mut(&(sender, app_name.to_string())),
+        };
+        if let Some(bucket) = bucket {
+            bucket.throttled.summary = Some(id);
+        }
+    }
+}
+
+/// Drops full buckets once there are too many, then the fullest ones, which
+/// limit their sender the least.
+fn prune<K: Clone + Eq + Hash>(
+    buckets: &mut HashMap<K, Bucket>,
+    burst: u32,
+    per_minute: u32,
+    now: Instant,
+) {
+    if buckets.len() <= MAX_BUCKETS {
+        return;
+    }
+    buckets.retain(|_, bucket| {
+        refill(bucket, burst, per_minute, now);
+        bucket.tokens < f64::from(burst)
+    });
+    if buckets.len() > MAX_BUCKETS {
+        let mut fullest: Vec<_> = buckets
+            .iter()
+            .map(|(key, bucket)| (bucket.tokens, key.clone()))
+            .collect();
+        fullest.sort_unstable_by(|a, b| b.0.total_cmp(&a.0));
+        for (_, key) in fullest.drain(..buckets.len() - MAX_BUCKETS) {
+            buckets.remove(&key);
+        }
+    }
+}
+
+fn refill(bucket: &mut Bucket, burst: u32, per_minute: u32, now: Instant) {
+    let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+    bucket.tokens = (bucket.tokens + elapsed * f64::from(per_minute) / 60.0).min(f64::from(burst));
+    bucket.updated = now;
+}
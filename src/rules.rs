@@ -0,0 +1,106 @@
+use cosmic_notifications_config::{Rule, RuleAction};
+use cosmic_notifications_util::{Hint, Notification};
+use regex::Regex;
+
+/// What should happen to a notification after the rules were applied.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Outcome {
+    pub drop: bool,
+    pub mute: bool,
+}
+
+#[derive(Debug)]
+struct CompiledRule {
+    rule: Rule,
+    summary: Option<Regex>,
+    body: Option<Regex>,
+}
+
+impl CompiledRule {
+    fn matches(&self, n: &Notification) -> bool {
+        let m = &self.rule.matches;
+        m.app_name.as_ref().is_none_or(|a| *a == n.app_name)
+            && m.desktop_entry
+                .as_deref()
+                .is_none_or(|d| n.desktop_entry() == Some(d))
+            && m.executable.as_deref().is_none_or(|e| {
+                n.provenance
+                    .as_ref()
+                    .and_then(|p| p.exe.as_deref())
+                    .is_some_and(|exe| exe == e)
+            })
+            && m.category.as_deref().is_none_or(|c| n.category_matches(c))
+            && m.urgency.is_none_or(|u| n.urgency() == u)
+            && self.summary.as_ref().is_none_or(|r| r.is_match(&n.summary))
+            && self.body.as_ref().is_none_or(|r| r.is_match(&n.body))
+    }
+}
+
+/// Notification rules from the config, with their regular expressions compiled.
+#[derive(Debug, Default)]
+pub struct Rules(Vec<CompiledRule>);
+
+impl Rules {
+    pub fn new(rules: &[Rule]) -> Self {
+        let compile = |re: &Option<String>| {
+            re.as_deref().and_then(|re| match Regex::new(re) {
+                Ok(re) => Some(re),
+                Err(err) => {
+                    tracing::error!("Invalid regex in notification rule: {}", err);
+                    None
+                }
+            })
+        };
+
+        Self(
+            rules
+                .iter()
+                .filter_map(|rule| {
+                    let summary = compile(&rule.matches.summary);
+                    let body = compile(&rule.matches.body);
+                    // Skip rules with invalid expressions instead of matching everything.
+                    if (summary.is_none() && rule.matches.summary.is_some())
+                        || (body.is_none() && rule.matches.body.is_some())
+                    {
+                        return None;
+                    }
+                    Some(CompiledRule {
+                        rule: rule.clone(),
+                        summary,
+                        body,
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    /// Applies the actions of every matching rule to the notification.
+    pub fn apply(&self, n: &mut Notification) -> Outcome {
+        let mut outcome = Outcome::default();
+
+        for rule in &self.0 {
+            if !rule.matches(n) {
+                continue;
+            }
+
+            for action in &rule.rule.actions {
+                match action {
+                    RuleAction::Drop => outcome.drop = true,
+                    RuleAction::Mute => outcome.mute = true,
+                    RuleAction::Urgency(urgency) => set_hint(n, Hint::Urgency(*urgency)),
+                    RuleAction::Timeout(timeout) => n.expire_timeout = *timeout,
+                    RuleAction::SuppressSound => set_hint(n, Hint::SuppressSound(true)),
+                    RuleAction::Transient => set_hint(n, Hint::Transient(true)),
+                }
+            }
+        }
+
+        outcome
+    }
+}
+
+fn set_hint(n: &mut Notification, hint: Hint) {
+    n.hints
+        .retain(|h| std::mem::discriminant(h) != std::mem::discriminant(&hint));
+    n.hints.push(hint);
+}
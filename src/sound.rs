@@ -0,0 +1,194 @@
+use crate::desktop::data_dirs;
+use cosmic_notifications_config::NotificationsConfig;
+use cosmic_notifications_util::{Hint, Notification};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{Arc, Mutex},
+};
+
+const SOUND_EXTENSIONS: &[&str] = &["oga", "ogg", "wav"];
+const FALLBACK_THEME: &str = "freedesktop";
+
+/// Plays sound files.
+pub trait Backend: Send + Sync {
+    fn play(&self, path: &Path);
+}
+
+/// Plays sounds with a command line player such as `pw-play` or `paplay`.
+#[derive(Debug)]
+pub struct CommandBackend(PathBuf);
+
+impl Backend for CommandBackend {
+    fn play(&self, path: &Path) {
+        match Command::new(&self.0).arg(path).status() {
+            Ok(status) if !status.success() => {
+                tracing::warn!("Failed to play {}: exited with {}", path.display(), status);
+            }
+            Err(err) => tracing::warn!("Failed to play {}: {}", path.display(), err),
+            Ok(_) => {}
+        }
+    }
+}
+
+/// Discards all sounds.
+#[derive(Debug)]
+pub struct NullBackend;
+
+impl Backend for NullBackend {
+    fn play(&self, _path: &Path) {}
+}
+
+/// Picks the PipeWire player if available, then the PulseAudio one.
+pub fn default_backend() -> Arc<dyn Backend> {
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    for player in ["pw-play", "paplay"] {
+        if let Some(player) = std::env::split_paths(&path)
+            .map(|dir| dir.join(player))
+            .find(|p| p.is_file())
+        {
+            return Arc::new(CommandBackend(player));
+        }
+    }
+
+    tracing::warn!("No sound player found, notification sounds are disabled");
+    Arc::new(NullBackend)
+}
+
+pub struct Sounds {
+    backend: Arc<dyn Backend>,
+    /// Resolved sound names by theme and name.
+    cache: Mutex<HashMap<(String, String), Option<PathBuf>>>,
+}
+
+impl Sounds {
+    pub fn new(backend: Arc<dyn Backend>) -> Self {
+        Self {
+            backend,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Plays the sound of a notification which is being shown.
+    pub fn play(&self, config: &NotificationsConfig, n: &Notification) {
+        let Some(path) = self.sound_for(config, n) else {
+            return;
+        };
+
+        let backend = self.backend.clone();
+        tokio::task::spawn_blocking(move || backend.play(&path));
+    }
+
+    fn sound_for(&self, config: &NotificationsConfig, n: &Notification) -> Option<PathBuf> {
+        if n.hints.contains(&Hint::SuppressSound(true)) {
+            return None;
+        }
+
+        let mut name = None;
+        for hint in &n.hints {
+            match hint {
+                Hint::SoundFile(path) => return Some(path.clone()),
+                Hint::SoundName(sound_name) => name = Some(sound_name.as_str()),
+                _ => {}
+            }
+        }
+
+        let name = name.or(match n.urgency() {
+            0 => config.sound_low.as_deref(),
+            1 => config.sound_normal.as_deref(),
+            _ => config.sound_critical.as_deref(),
+        })?;
+        self.lookup(&config.sound_theme, name)
+    }
+
+    fn lookup(&self, theme: &str, name: &str) -> Option<PathBuf> {
+        let mut cache = self.cache.lock().unwrap();
+        cache
+            .entry((theme.to_string(), name.to_string()))
+            .or_insert_with(|| {
+                let path = lookup_sound(theme, name);
+                if path.is_none() {
+                    tracing::warn!("Sound {} not found in theme {}", name, theme);
+                }
+                path
+            })
+            .clone()
+    }
+}
+
+/// Looks up a sound by name according to the freedesktop sound theme spec.
+pub fn lookup_sound(theme: &str, name: &str) -> Option<PathBuf> {
+    let data_dirs = data_dirs();
+    let mut themes = vec![theme.to_string()];
+    let mut i = 0;
+    while let Some(theme) = themes.get(i).cloned() {
+        i += 1;
+        let theme_dirs: Vec<PathBuf> = data_dirs
+            .iter()
+            .map(|d| d.join("sounds").join(&theme))
+            .filter(|d| d.is_dir())
+            .collect();
+
+        for theme_dir in &theme_dirs {
+            let (subdirs, inherits) = read_index(&theme_dir.join("index.theme"));
+            for parent in inherits {
+                if !themes.contains(&parent) {
+                    themes.push(parent);
+                }
+            }
+
+            // More specific names are tried first, so "message-new-instant" falls back
+            // to "message-new" and then "message".
+            let mut name = name;
+            loop {
+                for subdir in subdirs.iter().map(|s| theme_dir.join(s)) {
+                    for ext in SOUND_EXTENSIONS {
+                        let path = subdir.join(format!("{name}.{ext}"));
+                        if path.is_file() {
+                            return Some(path);
+                        }
+                    }
+                }
+                match name.rsplit_once('-') {
+                    Some((prefix, _)) => name = prefix,
+                    None => break,
+                }
+            }
+        }
+
+        if i == themes.len() && !themes.iter().any(|t| t == FALLBACK_THEME) {
+            themes.push(FALLBACK_THEME.to_string());
+        }
+    }
+
+    None
+}
+
+/// Reads the sound directories and inherited themes from an `index.theme`.
+fn read_index(path: &Path) -> (Vec<String>, Vec<String>) {
+    let mut subdirs = Vec::new();
+    let mut inherits = Vec::new();
+    if let Ok(index) = std::fs::read_to_string(path) {
+        let mut in_theme_section = false;
+        for line in index.lines().map(str::trim) {
+            if line.starts_with('[') {
+                in_theme_section = line == "[Sound Theme]";
+                continue;
+            }
+            if !in_theme_section {
+                continue;
+            }
+            if let Some(value) = line.strip_prefix("Directories=") {
+                subdirs.extend(value.split(',').map(|s| s.trim().to_string()));
+            } else if let Some(value) = line.strip_prefix("Inherits=") {
+                inherits.extend(value.split(',').map(|s| s.trim().to_string()));
+            }
+        }
+    }
+
+    if subdirs.is_empty() {
+        subdirs.push("stereo".to_string());
+    }
+    (subdirs, inherits)
+}
@@ -128,6 +128,9 @@ pub struct NotificationsApplet {
 
 #[interface(name = "com.system76.NotificationsApplet")]
 impl NotificationsApplet {
+    /// A notification was added or replaced. Despite its name, `replaces_id`
+    /// is the id of the notification, which is only known to the applet if it
+    /// was replaced.
     #[zbus(signal)]
     pub async fn notify(
         signal_ctxt: &SignalEmitter<'_>,
@@ -141,6 +144,22 @@ impl NotificationsApplet {
         expire_timeout: i32,
     ) -> zbus::Result<()>;
 
+    /// The notification with this id was updated in place. It is followed by
+    /// `Notify` with the same id for applets which do not handle this signal
+    /// yet, which applets handling it ignore.
+    #[zbus(signal)]
+    pub async fn replaced(
+        signal_ctxt: &SignalEmitter<'_>,
+        id: u32,
+        app_name: &str,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: Vec<&str>,
+        hints: HashMap<&str, zbus::zvariant::Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<()>;
+
     pub async fn invoke_action(&self, id: u32, action: &str) -> zbus::fdo::Result<()> {
         tracing::trace!("Received action from applet {id} {action}");
         let res = self
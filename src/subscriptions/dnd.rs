@@ -0,0 +1,31 @@
+use std::time::UNIX_EPOCH;
+
+use zbus::interface;
+
+use crate::dnd::Dnd;
+
+/// Exposes the Do Not Disturb state evaluated by the daemon.
+#[derive(Debug, Default)]
+pub struct DoNotDisturb {
+    pub dnd: Dnd,
+}
+
+#[interface(name = "com.system76.CosmicNotifications.DoNotDisturb")]
+impl DoNotDisturb {
+    #[zbus(property)]
+    async fn active(&self) -> bool {
+        self.dnd.is_active()
+    }
+
+    /// Seconds since the Unix epoch at which Do Not Disturb ends, or 0 if it
+    /// is inactive or has no end.
+    #[zbus(property)]
+    async fn until(&self) -> u64 {
+        match self.dnd {
+            Dnd::On { until: Some(until) } => {
+                until.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+            }
+            _ => 0,
+        }
+    }
+}
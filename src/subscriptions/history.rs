@@ -0,0 +1,173 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::UNIX_EPOCH,
+};
+
+use cosmic_notifications_util::{ActionId, Notification};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc::Sender, oneshot};
+use zbus::{fdo, interface, object_server::SignalEmitter, zvariant::Type};
+
+use super::notifications::Input;
+
+/// A notification as exposed by the history interface.
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct HistoryEntry {
+    pub id: u32,
+    pub app_name: String,
+    pub app_icon: String,
+    pub summary: String,
+    pub body: String,
+    /// Pairs of action keys and labels.
+    pub actions: Vec<(String, String)>,
+    pub category: String,
+    pub desktop_entry: String,
+    pub urgency: u8,
+    /// The progress in percent, or -1 if the notification shows none.
+    pub value: i32,
+    /// Milliseconds since the Unix epoch at which the notification was received.
+    pub time: u64,
+}
+
+impl From<&Notification> for HistoryEntry {
+    fn from(n: &Notification) -> Self {
+        Self {
+            id: n.id,
+            app_name: n.display_name().to_string(),
+            app_icon: n.display_icon().to_string(),
+            summary: n.summary.clone(),
+            body: n.body.clone(),
+            actions: n
+                .actions
+                .iter()
+                .map(|(id, label)| (id.to_string(), label.clone()))
+                .collect(),
+            category: n.category().unwrap_or_default().to_string(),
+            desktop_entry: n.desktop_entry().unwrap_or_default().to_string(),
+            urgency: n.urgency(),
+            value: n.value().map_or(-1, i32::from),
+            time: n
+                .time
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis() as u64),
+        }
+    }
+}
+
+/// Sends the response to a history request back to the D-Bus method call.
+#[derive(Debug, Clone)]
+pub struct Reply<T>(Arc<Mutex<Option<oneshot::Sender<T>>>>);
+
+impl<T> Reply<T> {
+    pub fn new() -> (Self, oneshot::Receiver<T>) {
+        let (tx, rx) = oneshot::channel();
+        (Self(Arc::new(Mutex::new(Some(tx)))), rx)
+    }
+
+    pub fn send(&self, value: T) {
+        if let Some(tx) = self.0.lock().unwrap().take() {
+            _ = tx.send(value);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Request {
+    ListActive(Reply<Vec<Notification>>),
+    ListHistory {
+        offset: usize,
+        limit: usize,
+        reply: Reply<Vec<Notification>>,
+    },
+    Get(u32, Reply<Option<Notification>>),
+    Remove(u32, Reply<bool>),
+    Clear(Reply<()>),
+    InvokeAction {
+        id: u32,
+        action: ActionId,
+        reply: Reply<bool>,
+    },
+}
+
+pub struct History {
+    tx: Sender<Input>,
+}
+
+impl History {
+    pub fn new(tx: Sender<Input>) -> Self {
+        Self { tx }
+    }
+
+    async fn request<T>(&self, request: impl FnOnce(Reply<T>) -> Request) -> fdo::Result<T> {
+        let (reply, rx) = Reply::new();
+        self.tx
+            .send(Input::History(request(reply)))
+            .await
+            .map_err(|err| fdo::Error::Failed(err.to_string()))?;
+        rx.await
+            .map_err(|_| fdo::Error::Failed("No response from the notifications daemon".into()))
+    }
+}
+
+#[interface(name = "com.system76.CosmicNotifications.History")]
+impl History {
+    /// Notifications which are currently displayed.
+    async fn list_active(&self) -> fdo::Result<Vec<HistoryEntry>> {
+        let active = self.request(Request::ListActive).await?;
+        Ok(active.iter().map(HistoryEntry::from).collect())
+    }
+
+    /// Notifications which are no longer displayed, newest first.
+    async fn list_history(&self, offset: u32, limit: u32) -> fdo::Result<Vec<HistoryEntry>> {
+        let history = self
+            .request(|reply| Request::ListHistory {
+                offset: offset as usize,
+                limit: limit as usize,
+                reply,
+            })
+            .await?;
+        Ok(history.iter().map(HistoryEntry::from).collect())
+    }
+
+    async fn get(&self, id: u32) -> fdo::Result<HistoryEntry> {
+        self.request(|reply| Request::Get(id, reply))
+            .await?
+            .as_ref()
+            .map(HistoryEntry::from)
+            .ok_or_else(|| fdo::Error::InvalidArgs(format!("Unknown notification {id}")))
+    }
+
+    async fn remove(&self, id: u32) -> fdo::Result<()> {
+        if self.request(|reply| Request::Remove(id, reply)).await? {
+            Ok(())
+        } else {
+            Err(fdo::Error::InvalidArgs(format!(
+                "Unknown notification {id}"
+            )))
+        }
+    }
+
+    async fn clear(&self) -> fdo::Result<()> {
+        self.request(Request::Clear).await
+    }
+
+    async fn invoke_action(&self, id: u32, key: &str) -> fdo::Result<()> {
+        let action = key.parse().unwrap();
+        if self
+            .request(|reply| Request::InvokeAction { id, action, reply })
+            .await?
+        {
+            Ok(())
+        } else {
+            Err(fdo::Error::InvalidArgs(format!(
+                "Unknown notification {id} or action {key}"
+            )))
+        }
+    }
+
+    #[zbus(signal)]
+    pub async fn added(signal_ctxt: &SignalEmitter<'_>, id: u32) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    pub async fn removed(signal_ctxt: &SignalEmitter<'_>, id: u32) -> zbus::Result<()>;
+}
@@ -0,0 +1,187 @@
+use cosmic::{
+    cctk::{
+        sctk::{
+            self,
+            reexports::{calloop, calloop_wayland_source::WaylandSource},
+            registry::{ProvidesRegistryState, RegistryState},
+            seat::{Capability, SeatHandler, SeatState},
+        },
+        wayland_client::{
+            Connection, Dispatch, QueueHandle, delegate_noop, globals::registry_queue_init,
+            protocol::wl_seat::WlSeat,
+        },
+        wayland_protocols::ext::idle_notify::v1::client::{
+            ext_idle_notification_v1::{self, ExtIdleNotificationV1},
+            ext_idle_notifier_v1::ExtIdleNotifierV1,
+        },
+    },
+    iced::{
+        Subscription,
+        futures::{SinkExt, StreamExt, channel::mpsc},
+        stream,
+    },
+};
+use std::time::Duration;
+use tracing::error;
+
+/// Whether the user is away, after `threshold` milliseconds without input on every seat.
+pub fn idle(threshold: u32) -> Subscription<bool> {
+    Subscription::run_with(threshold, |threshold| {
+        let threshold = *threshold;
+        stream::channel(10, move |mut output| async move {
+            let (tx, mut rx) = mpsc::unbounded();
+            std::thread::spawn(move || {
+                if let Err(err) = wayland_handler(tx, threshold) {
+                    error!("Failed to watch idle state {}", err);
+                }
+            });
+
+            while let Some(idle) = rx.next().await {
+                _ = output.send(idle).await;
+            }
+
+            cosmic::iced::futures::pending!();
+        })
+    })
+}
+
+struct Seat {
+    seat: WlSeat,
+    notification: ExtIdleNotificationV1,
+    idle: bool,
+}
+
+struct AppData {
+    tx: mpsc::UnboundedSender<bool>,
+    registry_state: RegistryState,
+    seat_state: SeatState,
+    notifier: ExtIdleNotifierV1,
+    threshold: u32,
+    seats: Vec<Seat>,
+    idle: bool,
+}
+
+impl AppData {
+    fn add_seat(&mut self, qh: &QueueHandle<Self>, seat: WlSeat) {
+        if self.seats.iter().any(|s| s.seat == seat) {
+            return;
+        }
+        let notification = self
+            .notifier
+            .get_idle_notification(self.threshold, &seat, qh, ());
+        self.seats.push(Seat {
+            seat,
+            notification,
+            idle: false,
+        });
+    }
+
+    fn update(&mut self) {
+        let idle = !self.seats.is_empty() && self.seats.iter().all(|s| s.idle);
+        if idle != self.idle {
+            self.idle = idle;
+            _ = self.tx.unbounded_send(idle);
+        }
+    }
+}
+
+impl ProvidesRegistryState for AppData {
+    fn registry(&mut self) -> &mut RegistryState {
+        &mut self.registry_state
+    }
+
+    sctk::registry_handlers!(SeatState);
+}
+
+impl SeatHandler for AppData {
+    fn seat_state(&mut self) -> &mut SeatState {
+        &mut self.seat_state
+    }
+
+    fn new_seat(&mut self, _conn: &Connection, qh: &QueueHandle<Self>, seat: WlSeat) {
+        self.add_seat(qh, seat);
+    }
+
+    fn new_capability(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _seat: WlSeat,
+        _capability: Capability,
+    ) {
+    }
+
+    fn remove_capability(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _seat: WlSeat,
+        _capability: Capability,
+    ) {
+    }
+
+    fn remove_seat(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, seat: WlSeat) {
+        if let Some(pos) = self.seats.iter().position(|s| s.seat == seat) {
+            self.seats.remove(pos).notification.destroy();
+        }
+        self.update();
+    }
+}
+
+impl Dispatch<ExtIdleNotificationV1, ()> for AppData {
+    fn event(
+        state: &mut Self,
+        proxy: &ExtIdleNotificationV1,
+        event: ext_idle_notification_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let idle = match event {
+            ext_idle_notification_v1::Event::Idled => true,
+            ext_idle_notification_v1::Event::Resumed => false,
+            _ => return,
+        };
+        if let Some(seat) = state.seats.iter_mut().find(|s| s.notification == *proxy) {
+            seat.idle = idle;
+        }
+        state.update();
+    }
+}
+
+fn wayland_handler(tx: mpsc::UnboundedSender<bool>, threshold: u32) -> anyhow::Result<()> {
+    let conn = Connection::connect_to_env()?;
+    let (globals, event_queue) = registry_queue_init(&conn)?;
+    let qh = event_queue.handle();
+
+    let mut event_loop = calloop::EventLoop::<AppData>::try_new()?;
+    WaylandSource::new(conn, event_queue)
+        .insert(event_loop.handle())
+        .map_err(|err| anyhow::anyhow!("{}", err))?;
+
+    let notifier = globals.bind::<ExtIdleNotifierV1, _, _>(&qh, 1..=1, ())?;
+    let mut app_data = AppData {
+        tx,
+        registry_state: RegistryState::new(&globals),
+        seat_state: SeatState::new(&globals, &qh),
+        notifier,
+        threshold,
+        seats: Vec::new(),
+        idle: false,
+    };
+    for seat in app_data.seat_state.seats().collect::<Vec<_>>() {
+        app_data.add_seat(&qh, seat);
+    }
+
+    // Stops once the subscription was dropped, which happens when the
+    // threshold changes or idle detection is turned off. Idle events are rare,
+    // so dispatching wakes up regularly to notice.
+    while !app_data.tx.is_closed() {
+        event_loop.dispatch(Some(Duration::from_secs(1)), &mut app_data)?;
+    }
+    Ok(())
+}
+
+delegate_noop!(AppData: ignore ExtIdleNotifierV1);
+sctk::delegate_seat!(AppData);
+sctk::delegate_registry!(AppData);
@@ -1,2 +1,10 @@
 pub mod applet;
+pub mod dnd;
+pub mod history;
+pub mod idle;
 pub mod notifications;
+pub mod screencast;
+pub mod toplevels;
+
+/// Object path of the daemon's own interfaces on the session bus.
+pub const DAEMON_PATH: &str = "/com/system76/CosmicNotifications";
@@ -1,35 +1,75 @@
-use crate::{config::VERSION, subscriptions::applet};
+use crate::{
+    config::VERSION,
+    desktop::Applications,
+    provenance,
+    rate_limit::{RateLimiter, Throttled},
+    rules::Rules,
+    subscriptions::applet,
+};
 use cosmic::iced::{
     Subscription,
     futures::{self, SinkExt},
     stream,
 };
-use cosmic_notifications_util::{ActionId, CloseReason, Notification};
+use cosmic_notifications_config::{NotificationsConfig, Rule};
+use cosmic_notifications_util::{
+    ActionId, BodyImage, CloseReason, Hint, Notification, Provenance, load_body_images,
+};
 use futures::channel::mpsc;
-use std::{collections::HashMap, fmt::Debug, num::NonZeroU32};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    num::NonZeroU32,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
 use tokio::{
-    sync::mpsc::{Receiver, Sender, channel},
+    sync::{
+        Semaphore,
+        mpsc::{Receiver, Sender, channel},
+    },
     task::JoinHandle,
 };
 use tracing::error;
 
 use zbus::{
-    Connection, connection::Builder as ConnectionBuilder, interface, object_server::SignalEmitter,
+    Connection,
+    connection::Builder as ConnectionBuilder,
+    interface,
+    message::Header,
+    names::{OwnedUniqueName, UniqueName},
+    object_server::SignalEmitter,
 };
 
 use super::applet::NotificationsApplet;
+use super::{
+    DAEMON_PATH,
+    dnd::DoNotDisturb,
+    history::{self, History},
+};
+use crate::dnd::Dnd;
+
+const NOTIFICATIONS_PATH: &str = "/org/freedesktop/Notifications";
+
+/// How many bodies have their images decoded at once. Notifications are shown
+/// without waiting for their images, which are added once they are loaded.
+const BODY_IMAGE_LOADS: usize = 2;
 
 #[derive(Debug)]
 pub struct Conns {
     notifications: Connection,
     pub tx: Sender<Input>,
     rx: Receiver<Input>,
+    clients: Arc<Mutex<Clients>>,
     _panel: Option<Connection>,
 }
 
 impl Conns {
-    pub async fn new() -> zbus::Result<Self> {
+    /// Serves the notification interfaces on the session bus. Ids are issued
+    /// after the ids of the notifications `restored` from the history.
+    pub async fn new(restored: &[u32]) -> zbus::Result<Self> {
         let (tx, rx) = channel(100);
+        let clients = Arc::new(Mutex::new(Clients::new(restored)));
         let panel = match applet::setup_panel_conn(tx.clone()).await {
             Ok(conn) => Some(conn),
             Err(err) => {
@@ -39,29 +79,23 @@ impl Conns {
         };
 
         for _ in 0..5 {
-            if let Some(conn) = ConnectionBuilder::session()
-                .ok()
-                .and_then(|conn| conn.name("org.freedesktop.Notifications").ok())
-                .and_then(|conn| {
-                    conn.serve_at(
-                        "/org/freedesktop/Notifications",
-                        Notifications(tx.clone(), NonZeroU32::new(1).unwrap(), Vec::new()),
-                    )
-                    .ok()
-                })
-                .map(ConnectionBuilder::build)
-            {
-                if let Ok(conn) = conn.await {
+            match Self::session(tx.clone(), clients.clone()).await {
+                Ok(conn) => {
                     return Ok(Self {
                         tx,
                         notifications: conn,
                         rx,
+                        clients,
                         _panel: panel,
                     });
                 }
-            } else {
-                error!("Failed to create connection at /org/freedesktop/Notifications");
-                tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+                Err(err) => {
+                    error!(
+                        "Failed to create connection at /org/freedesktop/Notifications {}",
+                        err
+                    );
+                    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+                }
             }
         }
 
@@ -69,6 +103,74 @@ impl Conns {
             "Failed to create the dbus server".to_string(),
         ))
     }
+
+    /// Serves the notification interfaces on an existing connection, such as
+    /// a private bus or a p2p socket, without connecting to the panel.
+    pub async fn with_connection(conn: Connection, restored: &[u32]) -> zbus::Result<Self> {
+        let (tx, rx) = channel(100);
+        let clients = Arc::new(Mutex::new(Clients::new(restored)));
+        serve(&conn, tx.clone(), clients.clone()).await?;
+        Ok(Self {
+            tx,
+            notifications: conn,
+            rx,
+            clients,
+            _panel: None,
+        })
+    }
+
+    async fn session(tx: Sender<Input>, clients: Arc<Mutex<Clients>>) -> zbus::Result<Connection> {
+        let conn = ConnectionBuilder::session()?.build().await?;
+        // Serve the interfaces before owning the name so no call arrives early.
+        serve(&conn, tx, clients).await?;
+        conn.request_name("org.freedesktop.Notifications").await?;
+        Ok(conn)
+    }
+
+    /// Emits signals about notification `id` only to its sender, unless
+    /// broadcasting is enabled or the sender is unknown.
+    fn emitter(&self, id: u32) -> zbus::Result<SignalEmitter<'static>> {
+        let emitter = SignalEmitter::new(&self.notifications, NOTIFICATIONS_PATH)?;
+        let clients = self.clients.lock().unwrap();
+        Ok(match clients.owners.get(&id) {
+            Some(Some(owner)) if !clients.broadcast => {
+                emitter.set_destination(UniqueName::from(owner.clone()).into())
+            }
+            _ => emitter,
+        })
+    }
+}
+
+/// Registers the daemon's interfaces on the object server of `conn`.
+async fn serve(
+    conn: &Connection,
+    tx: Sender<Input>,
+    clients: Arc<Mutex<Clients>>,
+) -> zbus::Result<()> {
+    let object_server = conn.object_server();
+    object_server
+        .at(
+            NOTIFICATIONS_PATH,
+            Notifications {
+                tx: tx.clone(),
+                applets: Vec::new(),
+                clients,
+                applications: Applications::default(),
+                image_loads: Arc::new(Semaphore::new(BODY_IMAGE_LOADS)),
+            },
+        )
+        .await?;
+    object_server.at(DAEMON_PATH, History::new(tx)).await?;
+    object_server
+        .at(DAEMON_PATH, DoNotDisturb::default())
+        .await?;
+    Ok(())
+}
+
+/// Handles the requests of the daemon's interfaces, forwarding events for the
+/// UI to `output`.
+pub async fn run(conns: Conns, output: mpsc::Sender<Event>) {
+    Machine::<Waiting>::new(None, output).exec(conns).await;
 }
 
 struct Start;
@@ -99,9 +201,9 @@ impl<S> Machine<S> {
 }
 
 impl Machine<Start> {
-    pub async fn exec(mut self) -> Result<(Machine<Waiting>, Conns), ()> {
+    pub async fn exec(mut self, restored: Arc<[u32]>) -> Result<(Machine<Waiting>, Conns), ()> {
         let handle: JoinHandle<zbus::Result<_>> = tokio::spawn(async move {
-            let conns = Conns::new().await?;
+            let conns = Conns::new(&restored).await?;
             Ok(conns)
         });
 
@@ -128,91 +230,130 @@ impl Machine<Waiting> {
             if let Some(next) = conns.rx.recv().await {
                 match next {
                     Input::Activated { token, id, action } => {
-                        let object_server = conns.notifications.object_server();
-                        let Ok(iface_ref) = object_server
-                            .interface::<_, Notifications>("/org/freedesktop/Notifications")
-                            .await
-                        else {
+                        let Ok(emitter) = conns.emitter(id) else {
                             continue;
                         };
 
                         if let Err(err) =
-                            Notifications::activation_token(iface_ref.signal_emitter(), id, &token)
-                                .await
+                            Notifications::activation_token(&emitter, id, &token).await
                         {
                             error!("Failed to signal notification with token {}", err);
                         }
 
-                        if let Err(err) =
-                            Notifications::action_invoked(iface_ref.signal_emitter(), id, &action)
-                                .await
+                        if let Err(err) = Notifications::action_invoked(&emitter, id, &action).await
                         {
                             error!("Failed to signal activated notification {}", err);
                         }
                         tracing::trace!("Activated application");
                     }
                     Input::Closed(id, reason) => {
-                        let object_server = conns.notifications.object_server();
-                        if let Ok(iface_ref) = object_server
-                            .interface::<_, Notifications>("/org/freedesktop/Notifications")
-                            .await
+                        if let Ok(emitter) = conns.emitter(id) {
+                            _ = Notifications::notification_closed(&emitter, id, reason as u32)
+                                .await;
+                        }
+                    }
+                    Input::Replied { id, text } => {
+                        if let Ok(emitter) = conns.emitter(id)
+                            && let Err(err) =
+                                Notifications::notification_replied(&emitter, id, &text).await
                         {
-                            _ = Notifications::notification_closed(
-                                iface_ref.signal_emitter(),
-                                id,
-                                reason as u32,
-                            )
-                            .await;
+                            error!("Failed to signal reply to notification {}", err);
                         }
                     }
                     Input::Notification(notification) => {
+                        let id = notification.id;
                         _ = self.output.send(Event::Notification(notification)).await;
+
+                        let object_server = conns.notifications.object_server();
+                        if let Ok(iface_ref) =
+                            object_server.interface::<_, History>(DAEMON_PATH).await
+                            && let Err(err) = History::added(iface_ref.signal_emitter(), id).await
+                        {
+                            error!("Failed to signal added notification {}", err);
+                        }
                     }
-                    Input::Replace(notification) => {
-                        _ = self.output.send(Event::Replace(notification)).await;
-                    }
-                    Input::CloseNotification(id) => {
-                        _ = self.output.send(Event::CloseNotification(id)).await;
+                    Input::Hidden {
+                        notification,
+                        replaces,
+                    } => {
+                        let id = notification.id;
+                        _ = self.output.send(Event::Hidden(notification)).await;
 
                         let object_server = conns.notifications.object_server();
-                        let Ok(iface_ref) = object_server
-                            .interface::<_, Notifications>("/org/freedesktop/Notifications")
-                            .await
-                        else {
-                            continue;
-                        };
-                        if let Err(err) =
-                            Notifications::notification_closed(iface_ref.signal_emitter(), id, 3)
-                                .await
+                        if !replaces
+                            && let Ok(iface_ref) =
+                                object_server.interface::<_, History>(DAEMON_PATH).await
+                            && let Err(err) = History::added(iface_ref.signal_emitter(), id).await
                         {
-                            error!("Failed to signal close notification {}", err);
+                            error!("Failed to signal added notification {}", err);
                         }
                     }
-                    Input::Dismissed(id) => {
+                    Input::BodyImages { id, body, images } => {
+                        _ = self
+                            .output
+                            .send(Event::BodyImages { id, body, images })
+                            .await;
+                    }
+                    Input::Removed(id) => {
+                        conns.clients.lock().unwrap().remove(id);
                         let object_server = conns.notifications.object_server();
-                        let Ok(iface_ref) = object_server
-                            .interface::<_, Notifications>("/org/freedesktop/Notifications")
-                            .await
-                        else {
-                            continue;
-                        };
-                        if let Err(err) =
-                            Notifications::notification_closed(iface_ref.signal_emitter(), id, 2)
-                                .await
+                        if let Ok(iface_ref) =
+                            object_server.interface::<_, History>(DAEMON_PATH).await
+                            && let Err(err) = History::removed(iface_ref.signal_emitter(), id).await
                         {
-                            error!("Failed to signal dismissed notification {}", err);
+                            error!("Failed to signal removed notification {}", err);
                         }
                     }
-                    Input::AppletConn(c) => {
+                    Input::History(request) => {
+                        _ = self.output.send(Event::History(request)).await;
+                    }
+                    Input::Broadcast(broadcast) => {
+                        conns.clients.lock().unwrap().broadcast = broadcast;
+                    }
+                    Input::RateLimit { burst, per_minute } => {
+                        conns
+                            .clients
+                            .lock()
+                            .unwrap()
+                            .limiter
+                            .set_limits(burst, per_minute);
+                    }
+                    Input::Rules(rules) => {
+                        conns.clients.lock().unwrap().rules = Rules::new(&rules);
+                    }
+                    Input::DoNotDisturb(dnd) => {
                         let object_server = conns.notifications.object_server();
                         let Ok(iface_ref) = object_server
-                            .interface::<_, Notifications>("/org/freedesktop/Notifications")
+                            .interface::<_, DoNotDisturb>(DAEMON_PATH)
                             .await
                         else {
                             continue;
                         };
                         let mut iface = iface_ref.get_mut().await;
-                        iface.2.push(c);
+                        iface.dnd = dnd;
+                        if let Err(err) = iface.active_changed(iface_ref.signal_emitter()).await {
+                            error!("Failed to signal do not disturb change {}", err);
+                        }
+                        if let Err(err) = iface.until_changed(iface_ref.signal_emitter()).await {
+                            error!("Failed to signal do not disturb change {}", err);
+                        }
+                    }
+                    Input::Replace(notification) => {
+                        _ = self.output.send(Event::Replace(notification)).await;
+                    }
+                    Input::CloseNotification(id) => {
+                        _ = self.output.send(Event::CloseNotification(id)).await;
+
+                        let Ok(emitter) = conns.emitter(id) else {
+                            continue;
+                        };
+                        if let Err(err) = Notifications::notification_closed(&emitter, id, 3).await
+                        {
+                            error!("Failed to signal close notification {}", err);
+                        }
+                    }
+                    Input::AppletConn(c) => {
+                        conns.clients.lock().unwrap().new_applets.push(c);
                     }
                     Input::AppletActivated { id, action } => {
                         if let Err(err) = self
@@ -247,10 +388,41 @@ pub enum Input {
     },
     Notification(Notification),
     Replace(Notification),
+    /// A notification muted by a rule, which goes to the history without
+    /// being shown.
+    Hidden {
+        notification: Notification,
+        replaces: bool,
+    },
+    /// The images of a notification with this body were loaded.
+    BodyImages {
+        id: u32,
+        body: String,
+        images: Vec<BodyImage>,
+    },
     CloseNotification(u32),
+    /// The notification was closed for a reason other than `CloseNotification`,
+    /// which the server signals itself.
     Closed(u32, CloseReason),
-    Dismissed(u32),
+    /// The notification was removed from both the active cards and the history.
+    Removed(u32),
+    /// The user replied to the notification inline.
+    Replied {
+        id: u32,
+        text: String,
+    },
     AppletConn(Connection),
+    History(history::Request),
+    DoNotDisturb(Dnd),
+    /// Whether signals about a notification are sent to every client.
+    Broadcast(bool),
+    /// The number of notifications a client can send at once and per minute.
+    RateLimit {
+        burst: u32,
+        per_minute: u32,
+    },
+    /// The rules applied to incoming notifications.
+    Rules(Vec<Rule>),
 }
 
 #[derive(Debug, Clone)]
@@ -258,34 +430,239 @@ pub enum Event {
     Ready(Sender<Input>),
     Notification(Notification),
     Replace(Notification),
+    /// A notification muted by a rule, to be added to the history directly.
+    Hidden(Notification),
+    /// The images of a notification, if its body is still `body`.
+    BodyImages {
+        id: u32,
+        body: String,
+        images: Vec<BodyImage>,
+    },
     CloseNotification(u32),
-    AppletActivated { id: u32, action: ActionId },
+    AppletActivated {
+        id: u32,
+        action: ActionId,
+    },
+    History(history::Request),
 }
 
-pub fn notifications() -> Subscription<Event> {
+/// Serves notifications, issuing ids after the ids of the notifications
+/// `restored` from the history.
+pub fn notifications(restored: Arc<[u32]>) -> Subscription<Event> {
     struct SomeWorker;
 
-    Subscription::run_with(std::any::TypeId::of::<SomeWorker>(), |_| {
-        stream::channel(100, |output| async move {
-            let machine = Machine::<Start>::new(None, output);
+    Subscription::run_with(
+        (std::any::TypeId::of::<SomeWorker>(), restored),
+        |(_, restored)| {
+            let restored = restored.clone();
+            stream::channel(100, move |output| async move {
+                let machine = Machine::<Start>::new(None, output);
+
+                if let Ok((waiting, conns)) = machine.exec(restored).await {
+                    waiting.exec(conns).await;
+                };
+
+                futures::pending!();
+            })
+        },
+    )
+}
+
+/// The state of the server which is updated by [`Input`] as well. It is
+/// shared with the loop handling the inputs instead of locking the interface,
+/// which `Notify` holds while it waits on the same loop, and is never locked
+/// across an `.await`.
+#[derive(Debug)]
+struct Clients {
+    next_id: NonZeroU32,
+    /// The unique bus name of the sender of each notification which was not
+    /// removed yet, or `None` on a peer-to-peer connection.
+    owners: HashMap<u32, Option<OwnedUniqueName>>,
+    /// Whether signals about a notification are sent to every client instead
+    /// of only its sender.
+    broadcast: bool,
+    /// Ids of the notifications restored from the history, which belong to
+    /// no client but are still in use.
+    restored: HashSet<u32>,
+    limiter: RateLimiter,
+    rules: Rules,
+    /// Applet connections which are picked up by the next `Notify`.
+    new_applets: Vec<Connection>,
+}
+
+impl Clients {
+    fn new(restored: &[u32]) -> Self {
+        let config = NotificationsConfig::default();
+        // Ids continue after the restored ones, which history clients still
+        // refer to.
+        let next_id = restored
+            .iter()
+            .max()
+            .and_then(|id| NonZeroU32::new(id.wrapping_add(1)))
+            .unwrap_or(NonZeroU32::MIN);
+        Self {
+            next_id,
+            owners: HashMap::new(),
+            broadcast: false,
+            restored: restored.iter().copied().collect(),
+            limiter: RateLimiter::new(config.rate_limit_burst, config.rate_limit_per_minute),
+            rules: Rules::new(&config.rules),
+            new_applets: Vec::new(),
+        }
+    }
+
+    /// Forgets a notification which is neither displayed nor in the history.
+    fn remove(&mut self, id: u32) {
+        self.owners.remove(&id);
+        self.restored.remove(&id);
+    }
+
+    /// Whether `id` was issued to `sender`.
+    fn owns(&self, id: u32, sender: &Option<OwnedUniqueName>) -> bool {
+        self.owners.get(&id).is_some_and(|owner| owner == sender)
+    }
+
+    /// Issues a new id to `sender`.
+    fn issue(&mut self, sender: Option<OwnedUniqueName>) -> u32 {
+        let id = self.next_id();
+        self.owners.insert(id, sender);
+        id
+    }
 
-            if let Ok((waiting, conns)) = machine.exec().await {
-                waiting.exec(conns).await;
+    fn next_id(&mut self) -> u32 {
+        loop {
+            let id = self.next_id;
+            self.next_id = match self.next_id.checked_add(1) {
+                Some(id) => id,
+                None => {
+                    tracing::warn!("Notification ID overflowed");
+                    NonZeroU32::new(1).unwrap()
+                }
             };
+            // Ids still in use are skipped after an overflow.
+            if !self.owners.contains_key(&id.get()) && !self.restored.contains(&id.get()) {
+                return id.get();
+            }
+        }
+    }
+}
 
-            futures::pending!();
-        })
-    })
+pub struct Notifications {
+    tx: Sender<Input>,
+    applets: Vec<Connection>,
+    clients: Arc<Mutex<Clients>>,
+    applications: Applications,
+    image_loads: Arc<Semaphore>,
 }
 
-pub struct Notifications(Sender<Input>, NonZeroU32, Vec<Connection>);
+impl Notifications {
+    /// Decodes the images of a body in the background, and sends them once
+    /// they are loaded.
+    fn load_body_images(&self, id: u32, body: String) {
+        let tx = self.tx.clone();
+        let image_loads = self.image_loads.clone();
+        tokio::spawn(async move {
+            // The permit is held until decoding finishes, so a slow file
+            // system can't tie up more blocking threads.
+            let Ok(permit) = image_loads.acquire_owned().await else {
+                return;
+            };
+            let load = tokio::task::spawn_blocking(move || {
+                let _permit = permit;
+                let images = load_body_images(&body);
+                (body, images)
+            });
+            match load.await {
+                Ok((body, images)) => {
+                    _ = tx.send(Input::BodyImages { id, body, images }).await;
+                }
+                Err(err) => tracing::error!("Failed to load body images: {}", err),
+            }
+        });
+    }
+
+    /// Drops a notification exceeding the rate of its sender, and shows how
+    /// many were dropped in a single card instead.
+    async fn throttle(
+        &mut self,
+        sender: Option<OwnedUniqueName>,
+        app_name: &str,
+        app_icon: &str,
+        throttled: Throttled,
+    ) -> u32 {
+        if throttled.count == 1 {
+            tracing::warn!("Throttling notifications of {app_name:?} from {sender:?}");
+        }
+
+        let (id, replaces) = match throttled.summary {
+            Some(id) => (id, true),
+            None => {
+                let mut clients = self.clients.lock().unwrap();
+                let id = clients.next_id();
+                clients
+                    .limiter
+                    .set_summary(sender.as_ref().map(|s| s.as_str()), app_name, id);
+                clients.owners.insert(id, sender);
+                (id, false)
+            }
+        };
+        let summary = if app_name.is_empty() {
+            format!("{} more notifications", throttled.count)
+        } else {
+            format!("{} more from {}", throttled.count, app_name)
+        };
+        let n = Notification::new(
+            app_name,
+            id,
+            app_icon,
+            &summary,
+            "",
+            Vec::new(),
+            HashMap::new(),
+            -1,
+        );
+        if let Err(err) = self
+            .tx
+            .send(if replaces {
+                Input::Replace(n)
+            } else {
+                Input::Notification(n)
+            })
+            .await
+        {
+            tracing::error!("Failed to send notification: {}", err);
+        }
+
+        // The dropped notification still gets an id, which is never displayed.
+        self.clients.lock().unwrap().next_id()
+    }
+}
 
 #[interface(name = "org.freedesktop.Notifications")]
 impl Notifications {
-    async fn close_notification(&self, id: u32) {
-        if let Err(err) = self.0.send(Input::CloseNotification(id)).await {
+    async fn close_notification(
+        &self,
+        #[zbus(header)] header: Header<'_>,
+        id: u32,
+    ) -> zbus::fdo::Result<()> {
+        let sender = header.sender().map(|s| OwnedUniqueName::from(s.to_owned()));
+        {
+            let clients = self.clients.lock().unwrap();
+            if !clients.owners.contains_key(&id) {
+                tracing::debug!("Ignoring request to close unknown notification {id}");
+                return Ok(());
+            }
+            if !clients.owns(id, &sender) {
+                return Err(zbus::fdo::Error::AccessDenied(format!(
+                    "Notification {id} belongs to another client"
+                )));
+            }
+        }
+
+        if let Err(err) = self.tx.send(Input::CloseNotification(id)).await {
             tracing::error!("Failed to send close notification: {}", err);
         }
+        Ok(())
     }
 
     /// "action-icons"	Supports using icons instead of text for displaying actions. Using icons for actions must be enabled on a per-notification basis using the "action-icons" hint.
@@ -304,11 +681,13 @@ impl Notifications {
             "icon-static",
             "persistence",
             "actions",
-            // TODO support these
             "action-icons",
-            "body-markup",
             "body-hyperlinks",
+            "body-images",
             "sound",
+            "inline-reply",
+            // TODO support these
+            "body-markup",
         ]
     }
 
@@ -340,6 +719,8 @@ impl Notifications {
     #[allow(clippy::too_many_arguments)]
     async fn notify(
         &mut self,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(connection)] conn: &Connection,
         app_name: &str,
         replaces_id: u32,
         app_icon: &str,
@@ -349,24 +730,40 @@ impl Notifications {
         hints: HashMap<&str, zbus::zvariant::Value<'_>>,
         expire_timeout: i32,
     ) -> u32 {
-        let id = if replaces_id == 0 {
-            let id = self.1;
-            self.1 = match self.1.checked_add(1) {
-                Some(id) => id,
-                None => {
-                    tracing::warn!("Notification ID overflowed");
-                    NonZeroU32::new(1).unwrap()
-                }
-            };
-            id.get()
-        } else {
+        let sender = header.sender().map(|s| OwnedUniqueName::from(s.to_owned()));
+
+        // Only the sender of a notification may replace it, and ids which were
+        // not issued by the server get a fresh one.
+        let replaces = replaces_id != 0 && self.clients.lock().unwrap().owns(replaces_id, &sender);
+        if replaces_id != 0 && !replaces {
+            tracing::warn!(
+                "Ignoring replaces_id {replaces_id} from {sender:?}, which is unknown or belongs to another client"
+            );
+        }
+
+        // Updates of a notification which is already shown, such as progress,
+        // do not add cards and are not limited.
+        if !replaces {
+            let checked = self.clients.lock().unwrap().limiter.check(
+                sender.as_ref().map(|s| s.as_str()),
+                app_name,
+                Instant::now(),
+            );
+            if let Err(throttled) = checked {
+                return self.throttle(sender, app_name, app_icon, throttled).await;
+            }
+        }
+
+        let id = if replaces {
             replaces_id
+        } else {
+            self.clients.lock().unwrap().issue(sender.clone())
         };
         let hints_clone = hints
             .iter()
             .filter_map(|(k, v)| Some((*k, v.try_clone().ok()?)))
             .collect();
-        let n = Notification::new(
+        let mut n = Notification::new(
             app_name,
             id,
             app_icon,
@@ -376,10 +773,30 @@ impl Notifications {
             hints_clone,
             expire_timeout,
         );
+        if let Some(sender) = &sender {
+            n.provenance = provenance::resolve(conn, sender).await;
+        }
+        match n.provenance.as_ref().and_then(Provenance::sandboxed_app_id) {
+            Some(app_id) => self.applications.identify(&mut n, &app_id),
+            None => self.applications.resolve(&mut n),
+        }
+        n.resolve_action_icons();
+
+        let outcome = self.clients.lock().unwrap().rules.apply(&mut n);
+        if outcome.drop {
+            tracing::debug!("Dropped notification {id} by rule");
+            // The notification it replaces is kept, if there is one.
+            if !replaces {
+                self.clients.lock().unwrap().remove(id);
+            }
+            return id;
+        }
 
+        self.applets
+            .extend(self.clients.lock().unwrap().new_applets.drain(..));
         if !n.transient() {
-            let mut new_conns = Vec::with_capacity(self.2.len());
-            for c in self.2.drain(..) {
+            let mut new_conns = Vec::with_capacity(self.applets.len());
+            for c in self.applets.drain(..) {
                 let object_server = c.object_server();
                 let Ok(Ok(iface_ref)) = tokio::time::timeout(
                     tokio::time::Duration::from_millis(100),
@@ -390,46 +807,79 @@ impl Notifications {
                 else {
                     continue;
                 };
-                let hints_clone = hints
+                let mut hints_clone: HashMap<_, _> = hints
                     .iter()
                     .filter_map(|(k, v)| Some((*k, v.try_clone().ok()?)))
                     .collect();
-                match tokio::time::timeout(
-                    tokio::time::Duration::from_millis(500),
+                // The applet shows the identity the server resolved and the
+                // urgency set by rules as well.
+                if let Some(desktop_entry) = n.desktop_entry() {
+                    hints_clone.insert("desktop-entry", desktop_entry.into());
+                }
+                hints_clone.insert("urgency", n.urgency().into());
+                if n.hints.contains(&Hint::SuppressSound(true)) {
+                    hints_clone.insert("suppress-sound", true.into());
+                }
+                let emitter = iface_ref.signal_emitter();
+                let signal = async {
+                    if replaces {
+                        NotificationsApplet::replaced(
+                            emitter,
+                            id,
+                            &n.app_name,
+                            &n.app_icon,
+                            summary,
+                            body,
+                            actions.clone(),
+                            hints_clone
+                                .iter()
+                                .filter_map(|(k, v)| Some((*k, v.try_clone().ok()?)))
+                                .collect(),
+                            n.expire_timeout,
+                        )
+                        .await?;
+                    }
+                    // The applet does not handle `Replaced` yet, and replaces
+                    // the notification with the same id instead.
                     NotificationsApplet::notify(
-                        iface_ref.signal_emitter(),
-                        app_name,
+                        emitter,
+                        &n.app_name,
                         id,
-                        app_icon,
+                        &n.app_icon,
                         summary,
                         body,
                         actions.clone(),
                         hints_clone,
-                        expire_timeout,
-                    ),
-                )
-                .await
-                {
+                        n.expire_timeout,
+                    )
+                    .await
+                };
+                match tokio::time::timeout(tokio::time::Duration::from_millis(500), signal).await {
                     Ok(Err(err)) => error!("Failed to notify applet of notification {}", err),
                     Err(err) => error!("Failed to notify applet of notification {}", err),
                     Ok(_) => {}
                 }
                 new_conns.push(c);
             }
-            self.2 = new_conns;
+            self.applets = new_conns;
         }
 
-        if let Err(err) = self
-            .0
-            .send(if replaces_id == 0 {
-                Input::Notification(n)
-            } else {
-                Input::Replace(n)
-            })
-            .await
-        {
+        let input = if outcome.mute {
+            Input::Hidden {
+                notification: n,
+                replaces,
+            }
+        } else if replaces {
+            Input::Replace(n)
+        } else {
+            Input::Notification(n)
+        };
+        if let Err(err) = self.tx.send(input).await {
             tracing::error!("Failed to send notification: {}", err);
         }
+        if body.contains("<img") {
+            self.load_body_images(id, body.to_string());
+        }
 
         id
     }
@@ -466,4 +916,12 @@ impl Notifications {
         id: u32,
         reason: u32,
     ) -> zbus::Result<()>;
+
+    /// Sent with the text the user typed to reply to the notification `id`.
+    #[zbus(signal)]
+    async fn notification_replied(
+        signal_ctxt: &SignalEmitter<'_>,
+        id: u32,
+        text: &str,
+    ) -> zbus::Result<()>;
 }
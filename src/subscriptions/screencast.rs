@@ -0,0 +1,192 @@
+use cosmic::iced::{
+    Subscription,
+    futures::{SinkExt, StreamExt, channel::mpsc},
+    stream,
+};
+use std::collections::{HashMap, HashSet};
+use tracing::{error, warn};
+use zbus::{
+    Connection, MatchRule, MessageStream,
+    fdo::MonitoringProxy,
+    message::Type,
+    zvariant::{OwnedObjectPath, OwnedValue},
+};
+
+/// The bus name of the portal backend answering the screencast calls.
+pub const PORTAL_BACKEND: &str = "org.freedesktop.impl.portal.desktop.cosmic";
+
+/// The interfaces between `xdg-desktop-portal` and its backend.
+const SCREENCAST: &str = "org.freedesktop.impl.portal.ScreenCast";
+const SESSION: &str = "org.freedesktop.impl.portal.Session";
+
+/// Whether the desktop portal is recording or sharing the screen.
+pub fn screencast() -> Subscription<bool> {
+    struct ScreencastWorker;
+
+    Subscription::run_with(std::any::TypeId::of::<ScreencastWorker>(), |_| {
+        stream::channel(10, |output| async move {
+            match Connection::session().await {
+                Ok(conn) => {
+                    if let Err(err) = watch(conn, output).await {
+                        error!("Failed to watch screencasts {}", err);
+                    }
+                }
+                Err(err) => error!("Failed to connect to the session bus {}", err),
+            }
+
+            cosmic::iced::futures::pending!();
+        })
+    })
+}
+
+/// Turns `conn` into a monitor of the portal's screencast sessions, and sends
+/// whether any is active, starting with `false` once monitoring started.
+///
+/// The portal backend offers no signal for its sessions, so this watches its
+/// private traffic with the portal. If the bus refuses to monitor it, `false`
+/// is sent once and screencasts are never detected.
+pub async fn watch(conn: Connection, mut output: mpsc::Sender<bool>) -> zbus::Result<()> {
+    let rules = [
+        MatchRule::builder()
+            .msg_type(Type::MethodCall)
+            .interface(SCREENCAST)?
+            .member("Start")?
+            .build(),
+        MatchRule::builder()
+            .msg_type(Type::MethodCall)
+            .interface(SESSION)?
+            .member("Close")?
+            .build(),
+        MatchRule::builder()
+            .msg_type(Type::Signal)
+            .interface(SESSION)?
+            .member("Closed")?
+            .build(),
+        // Replies can't be matched by the call they answer, only by the
+        // backend sending them.
+        MatchRule::builder()
+            .msg_type(Type::MethodReturn)
+            .sender(PORTAL_BACKEND)?
+            .build(),
+        MatchRule::builder()
+            .msg_type(Type::Error)
+            .sender(PORTAL_BACKEND)?
+            .build(),
+    ];
+    let mut stream = MessageStream::from(&conn);
+    let mut sessions = Sessions::default();
+    let mut active = false;
+    if let Err(err) = MonitoringProxy::new(&conn)
+        .await?
+        .become_monitor(&rules, 0)
+        .await
+    {
+        warn!(
+            "The session bus refused to monitor screencasts, which will not activate Do Not Disturb: {err}"
+        );
+        _ = output.send(active).await;
+        return Ok(());
+    }
+    _ = output.send(active).await;
+    while let Some(msg) = stream.next().await {
+        let Ok(msg) = msg else {
+            continue;
+        };
+        let header = msg.header();
+        let member = header.member().map(|m| m.as_str());
+        let interface = header.interface().map(|i| i.as_str());
+        match msg.message_type() {
+            Type::MethodCall if interface == Some(SCREENCAST) && member == Some("Start") => {
+                let body = msg.body();
+                if let Some(sender) = header.sender()
+                    && let Ok((_, session, ..)) = body.deserialize::<(
+                        OwnedObjectPath,
+                        OwnedObjectPath,
+                        String,
+                        String,
+                        HashMap<String, OwnedValue>,
+                    )>()
+                {
+                    sessions.start(
+                        sender.as_str(),
+                        header.primary().serial_num().get(),
+                        session.as_str(),
+                    );
+                }
+            }
+            Type::MethodCall | Type::Signal if interface == Some(SESSION) => {
+                if let Some(path) = header.path() {
+                    sessions.close(path.as_str());
+                }
+            }
+            reply @ (Type::MethodReturn | Type::Error) => {
+                let (Some(destination), Some(serial)) =
+                    (header.destination(), header.reply_serial())
+                else {
+                    continue;
+                };
+                let Some(session) = sessions.take_starting(destination.as_str(), serial.get())
+                else {
+                    continue;
+                };
+                // The portal responds 0 if the user allowed the screencast.
+                if reply == Type::MethodReturn
+                    && msg
+                        .body()
+                        .deserialize::<(u32, HashMap<String, OwnedValue>)>()
+                        .is_ok_and(|(response, _)| response == 0)
+                {
+                    sessions.activate(session);
+                }
+            }
+            _ => {}
+        }
+
+        if sessions.is_active() != active {
+            active = sessions.is_active();
+            _ = output.send(active).await;
+        }
+    }
+
+    // Sessions can no longer be followed once the connection is lost.
+    if active {
+        _ = output.send(false).await;
+    }
+    Ok(())
+}
+
+/// Screencast sessions of the portal backend, followed from the messages
+/// between the portal and its backend.
+#[derive(Debug, Default)]
+pub struct Sessions {
+    /// Sessions whose `Start` call was not answered yet, by the caller and
+    /// serial of the call.
+    starting: HashMap<(String, u32), String>,
+    active: HashSet<String>,
+}
+
+impl Sessions {
+    /// The portal asked its backend to start the screencast `session`.
+    pub fn start(&mut self, caller: &str, serial: u32, session: &str) {
+        self.starting
+            .insert((caller.to_string(), serial), session.to_string());
+    }
+
+    /// Takes the session which the reply to the call `serial` of `caller` answers.
+    pub fn take_starting(&mut self, caller: &str, serial: u32) -> Option<String> {
+        self.starting.remove(&(caller.to_string(), serial))
+    }
+
+    pub fn activate(&mut self, session: String) {
+        self.active.insert(session);
+    }
+
+    pub fn close(&mut self, session: &str) {
+        self.active.remove(session);
+        self.starting.retain(|_, s| s != session);
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.active.is_empty()
+    }
+}
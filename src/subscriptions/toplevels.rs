@@ -0,0 +1,163 @@
+use std::{collections::HashSet, time::Duration};
+
+use cosmic::{
+    cctk::{
+        self,
+        cosmic_protocols::toplevel_info::v1::client::zcosmic_toplevel_handle_v1,
+        sctk::{
+            self,
+            output::{OutputHandler, OutputState},
+            reexports::{calloop, calloop_wayland_source::WaylandSource},
+            registry::{ProvidesRegistryState, RegistryState},
+        },
+        toplevel_info::{ToplevelInfoHandler, ToplevelInfoState},
+        wayland_client::{
+            Connection, QueueHandle, globals::registry_queue_init, protocol::wl_output::WlOutput,
+        },
+        wayland_protocols::ext::foreign_toplevel_list::v1::client::ext_foreign_toplevel_handle_v1::ExtForeignToplevelHandleV1,
+    },
+    iced::{
+        Subscription,
+        futures::{SinkExt, StreamExt, channel::mpsc},
+        stream,
+    },
+};
+use tracing::error;
+
+/// Whether a fullscreen toplevel is shown on the active output, which has the
+/// activated toplevel and is where notifications appear.
+pub fn fullscreen() -> Subscription<bool> {
+    struct FullscreenWorker;
+
+    Subscription::run_with(std::any::TypeId::of::<FullscreenWorker>(), |_| {
+        stream::channel(10, |mut output| async move {
+            let (tx, mut rx) = mpsc::unbounded();
+            std::thread::spawn(move || {
+                if let Err(err) = wayland_handler(tx) {
+                    error!("Failed to watch toplevels {}", err);
+                }
+            });
+
+            while let Some(fullscreen) = rx.next().await {
+                _ = output.send(fullscreen).await;
+            }
+
+            cosmic::iced::futures::pending!();
+        })
+    })
+}
+
+struct AppData {
+    tx: mpsc::UnboundedSender<bool>,
+    registry_state: RegistryState,
+    output_state: OutputState,
+    toplevel_info_state: ToplevelInfoState,
+    fullscreen: bool,
+}
+
+impl AppData {
+    fn update(&mut self) {
+        let outputs = |state: zcosmic_toplevel_handle_v1::State| {
+            self.toplevel_info_state
+                .toplevels()
+                .filter(|info| info.state.contains(&state))
+                .flat_map(|info| info.output.iter())
+                .collect::<HashSet<&WlOutput>>()
+        };
+        let fullscreen = !outputs(zcosmic_toplevel_handle_v1::State::Activated)
+            .is_disjoint(&outputs(zcosmic_toplevel_handle_v1::State::Fullscreen));
+
+        if fullscreen != self.fullscreen {
+            self.fullscreen = fullscreen;
+            _ = self.tx.unbounded_send(fullscreen);
+        }
+    }
+}
+
+impl ProvidesRegistryState for AppData {
+    fn registry(&mut self) -> &mut RegistryState {
+        &mut self.registry_state
+    }
+
+    sctk::registry_handlers!(OutputState);
+}
+
+impl OutputHandler for AppData {
+    fn output_state(&mut self) -> &mut OutputState {
+        &mut self.output_state
+    }
+
+    fn new_output(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _output: WlOutput) {}
+
+    fn update_output(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _output: WlOutput) {
+        self.update();
+    }
+
+    fn output_destroyed(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _output: WlOutput) {
+        self.update();
+    }
+}
+
+impl ToplevelInfoHandler for AppData {
+    fn toplevel_info_state(&mut self) -> &mut ToplevelInfoState {
+        &mut self.toplevel_info_state
+    }
+
+    fn new_toplevel(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _toplevel: &ExtForeignToplevelHandleV1,
+    ) {
+        self.update();
+    }
+
+    fn update_toplevel(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _toplevel: &ExtForeignToplevelHandleV1,
+    ) {
+        self.update();
+    }
+
+    fn toplevel_closed(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _toplevel: &ExtForeignToplevelHandleV1,
+    ) {
+        self.update();
+    }
+}
+
+fn wayland_handler(tx: mpsc::UnboundedSender<bool>) -> anyhow::Result<()> {
+    let conn = Connection::connect_to_env()?;
+    let (globals, event_queue) = registry_queue_init(&conn)?;
+    let qh = event_queue.handle();
+
+    let mut event_loop = calloop::EventLoop::<AppData>::try_new()?;
+    WaylandSource::new(conn, event_queue)
+        .insert(event_loop.handle())
+        .map_err(|err| anyhow::anyhow!("{}", err))?;
+
+    let registry_state = RegistryState::new(&globals);
+    let mut app_data = AppData {
+        tx,
+        output_state: OutputState::new(&globals, &qh),
+        toplevel_info_state: ToplevelInfoState::new(&registry_state, &qh),
+        registry_state,
+        fullscreen: false,
+    };
+
+    // Stops once the subscription was dropped, which happens when the setting
+    // is turned off. Dispatching wakes up regularly to notice without events.
+    while !app_data.tx.is_closed() {
+        event_loop.dispatch(Some(Duration::from_secs(1)), &mut app_data)?;
+    }
+    Ok(())
+}
+
+sctk::delegate_output!(AppData);
+sctk::delegate_registry!(AppData);
+cctk::delegate_toplevel_info!(AppData);
@@ -0,0 +1,243 @@
+use std::{fs, path::PathBuf};
+
+use cosmic_notifications::desktop::{Applications, DesktopEntry, exec_args};
+use cosmic_notifications_util::{Hint, Notification};
+
+/// Creates an applications directory for each list of desktop files.
+fn applications(name: &str, dirs: &[&[(&str, &str)]]) -> (Applications, PathBuf) {
+    let root = std::env::temp_dir().join(format!(
+        "cosmic-notifications-{name}-{}",
+        std::process::id()
+    ));
+    let mut paths = Vec::new();
+    for (i, files) in dirs.iter().enumerate() {
+        let dir = root.join(i.to_string());
+        fs::create_dir_all(&dir).unwrap();
+        for (file, contents) in *files {
+            fs::write(dir.join(file), contents).unwrap();
+        }
+        paths.push(dir);
+    }
+    (Applications::with_dirs(paths), root)
+}
+
+const FIREFOX: &str = "[Desktop Entry]\nName=Firefox\nName[de]=Firefox-Browser\nIcon=org.mozilla.firefox\nExec=firefox %u\n\n[Desktop Action new-window]\nName=New Window\n";
+
+#[test]
+fn finds_name_and_icon() {
+    let (apps, root) = applications("find", &[&[("org.mozilla.firefox.desktop", FIREFOX)]]);
+
+    assert_eq!(
+        apps.find("org.mozilla.firefox"),
+        Some(DesktopEntry {
+            name: String::from("Firefox"),
+            icon: Some(String::from("org.mozilla.firefox")),
+            exec: Some(String::from("firefox %u")),
+            dbus_activatable: false,
+        })
+    );
+    assert_eq!(apps.find("org.example.Missing"), None);
+    _ = fs::remove_dir_all(root);
+}
+
+#[test]
+fn earlier_dirs_take_precedence() {
+    let (apps, root) = applications(
+        "precedence",
+        &[
+            &[("app.desktop", "[Desktop Entry]\nName=Local\n")],
+            &[("app.desktop", "[Desktop Entry]\nName=System\nIcon=app\n")],
+        ],
+    );
+
+    assert_eq!(
+        apps.find("app"),
+        Some(DesktopEntry {
+            name: String::from("Local"),
+            icon: None,
+            exec: None,
+            dbus_activatable: false,
+        })
+    );
+    _ = fs::remove_dir_all(root);
+}
+
+#[test]
+fn ids_cannot_leave_the_dirs() {
+    let (apps, root) = applications("escape", &[&[]]);
+    fs::write(
+        root.join("secret.desktop"),
+        "[Desktop Entry]\nName=Secret\n",
+    )
+    .unwrap();
+
+    assert_eq!(apps.find("../secret"), None);
+    assert_eq!(apps.find(""), None);
+    _ = fs::remove_dir_all(root);
+}
+
+#[test]
+fn identify_replaces_reported_identity() {
+    let (apps, root) = applications("identify", &[&[("org.mozilla.firefox.desktop", FIREFOX)]]);
+    let mut n = Notification::new(
+        "Totally Not Firefox",
+        1,
+        "fake-icon",
+        "Summary",
+        "",
+        Vec::new(),
+        Default::default(),
+        -1,
+    );
+    n.hints.push(Hint::DesktopEntry(String::from("fake")));
+
+    apps.identify(&mut n, "org.mozilla.firefox");
+    assert_eq!(n.display_name(), "Firefox");
+    assert_eq!(n.display_icon(), "org.mozilla.firefox");
+    assert_eq!(n.desktop_entry(), Some("org.mozilla.firefox"));
+    assert_eq!(
+        n.hints
+            .iter()
+            .filter(|h| matches!(h, Hint::DesktopEntry(_)))
+            .count(),
+        1
+    );
+
+    apps.identify(&mut n, "org.example.Unknown");
+    assert_eq!(n.display_name(), "org.example.Unknown");
+    assert_eq!(n.display_icon(), "");
+    _ = fs::remove_dir_all(root);
+}
+
+#[test]
+fn prefers_translated_name() {
+    let (apps, root) = applications("locale", &[&[("org.mozilla.firefox.desktop", FIREFOX)]]);
+    let apps = apps.with_locale("de_DE.UTF-8");
+
+    assert_eq!(
+        apps.find("org.mozilla.firefox").map(|entry| entry.name),
+        Some(String::from("Firefox-Browser"))
+    );
+    _ = fs::remove_dir_all(root);
+}
+
+#[test]
+fn untranslated_name_is_the_fallback() {
+    let (_, root) = applications(
+        "fallback-locale",
+        &[&[("org.mozilla.firefox.desktop", FIREFOX)]],
+    );
+
+    for locale in ["fr_FR.UTF-8", "C", ""] {
+        let apps = Applications::with_dirs(vec![root.join("0")]).with_locale(locale);
+        assert_eq!(
+            apps.find("org.mozilla.firefox").map(|entry| entry.name),
+            Some(String::from("Firefox"))
+        );
+    }
+    _ = fs::remove_dir_all(root);
+}
+
+#[test]
+fn found_entries_are_cached() {
+    let (apps, root) = applications("cache", &[&[("org.mozilla.firefox.desktop", FIREFOX)]]);
+
+    assert!(apps.find("org.mozilla.firefox").is_some());
+    fs::remove_dir_all(&root).unwrap();
+    assert!(apps.find("org.mozilla.firefox").is_some());
+}
+
+fn with_desktop_entry(app_name: &str, app_icon: &str, desktop_entry: Option<&str>) -> Notification {
+    let mut n = Notification::new(
+        app_name,
+        1,
+        app_icon,
+        "Summary",
+        "",
+        Vec::new(),
+        Default::default(),
+        -1,
+    );
+    n.hints
+        .extend(desktop_entry.map(|d| Hint::DesktopEntry(d.to_string())));
+    n
+}
+
+#[test]
+fn resolve_uses_desktop_entry_hint() {
+    let (apps, root) = applications("resolve", &[&[("org.mozilla.firefox.desktop", FIREFOX)]]);
+
+    let mut n = with_desktop_entry("firefox", "", Some("org.mozilla.firefox"));
+    apps.resolve(&mut n);
+    assert_eq!(n.display_name(), "Firefox");
+    assert_eq!(n.display_icon(), "org.mozilla.firefox");
+    // Rules and Do Not Disturb still see the reported identity.
+    assert_eq!(n.app_name, "firefox");
+    assert_eq!(n.app_icon, "");
+
+    let mut n = with_desktop_entry(
+        "firefox",
+        "custom-icon",
+        Some("org.mozilla.firefox.desktop"),
+    );
+    apps.resolve(&mut n);
+    assert_eq!(n.display_name(), "Firefox");
+    assert_eq!(n.display_icon(), "custom-icon");
+    _ = fs::remove_dir_all(root);
+}
+
+#[test]
+fn resolve_falls_back_to_app_name() {
+    let (apps, root) = applications("resolve-fallback", &[&[]]);
+
+    let mut n = with_desktop_entry("firefox", "", Some("org.mozilla.firefox"));
+    apps.resolve(&mut n);
+    assert_eq!(n.display_name(), "firefox");
+
+    let mut n = with_desktop_entry("firefox", "", None);
+    apps.resolve(&mut n);
+    assert_eq!(n.display_name(), "firefox");
+    _ = fs::remove_dir_all(root);
+}
+
+#[test]
+fn reads_dbus_activatable() {
+    let (apps, root) = applications(
+        "dbus-activatable",
+        &[&[(
+            "org.gnome.Nautilus.desktop",
+            "[Desktop Entry]\nName=Files\nExec=nautilus --new-window %U\nDBusActivatable=true\n",
+        )]],
+    );
+
+    let entry = apps.find("org.gnome.Nautilus").unwrap();
+    assert!(entry.dbus_activatable);
+    assert_eq!(entry.exec.as_deref(), Some("nautilus --new-window %U"));
+    _ = fs::remove_dir_all(root);
+}
+
+#[test]
+fn exec_args_leave_out_field_codes() {
+    assert_eq!(exec_args("firefox %u"), ["firefox"]);
+    assert_eq!(
+        exec_args(
+            "/usr/bin/flatpak run --branch=stable --command=firefox org.mozilla.firefox @@u %u @@"
+        ),
+        [
+            "/usr/bin/flatpak",
+            "run",
+            "--branch=stable",
+            "--command=firefox",
+            "org.mozilla.firefox"
+        ]
+    );
+    assert_eq!(exec_args("app --progress=50%%"), ["app", "--progress=50%"]);
+}
+
+#[test]
+fn exec_args_are_unquoted() {
+    assert_eq!(
+        exec_args(r#""/opt/My App/app" --title "say \"hi\"" """#),
+        ["/opt/My App/app", "--title", "say \"hi\"", ""]
+    );
+}
@@ -0,0 +1,105 @@
+use std::time::SystemTime;
+
+use chrono::{DateTime, Local, TimeZone};
+use cosmic_notifications::dnd::{Dnd, state};
+use cosmic_notifications_config::{DndSchedule, NotificationsConfig, Weekday};
+
+/// A local time on `day` of January 2026, in which the 14th is a Wednesday.
+fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+    Local
+        .with_ymd_and_hms(2026, 1, day, hour, minute, 0)
+        .earliest()
+        .unwrap()
+}
+
+fn until(time: DateTime<Local>) -> Dnd {
+    Dnd::On {
+        until: Some(SystemTime::from(time)),
+    }
+}
+
+fn scheduled(days: Vec<Weekday>, start: u16, end: u16) -> NotificationsConfig {
+    NotificationsConfig {
+        do_not_disturb_schedules: vec![DndSchedule { days, start, end }],
+        ..NotificationsConfig::default()
+    }
+}
+
+#[test]
+fn off_by_default() {
+    assert_eq!(
+        state(&NotificationsConfig::default(), at(14, 12, 0)),
+        Dnd::Off
+    );
+}
+
+#[test]
+fn manual_toggle_is_indefinite() {
+    let config = NotificationsConfig {
+        do_not_disturb: true,
+        ..NotificationsConfig::default()
+    };
+
+    assert_eq!(state(&config, at(14, 12, 0)), Dnd::On { until: None });
+}
+
+#[test]
+fn schedule_is_active_within_window() {
+    let config = scheduled(Vec::new(), 12 * 60, 13 * 60);
+
+    assert_eq!(state(&config, at(14, 11, 59)), Dnd::Off);
+    assert_eq!(state(&config, at(14, 12, 0)), until(at(14, 13, 0)));
+    assert_eq!(state(&config, at(17, 12, 30)), until(at(17, 13, 0)));
+    assert_eq!(state(&config, at(14, 13, 0)), Dnd::Off);
+}
+
+#[test]
+fn schedule_crosses_midnight() {
+    let config = scheduled(Vec::new(), 22 * 60, 7 * 60);
+
+    assert_eq!(state(&config, at(14, 21, 0)), Dnd::Off);
+    assert_eq!(state(&config, at(14, 23, 0)), until(at(15, 7, 0)));
+    assert_eq!(state(&config, at(15, 6, 0)), until(at(15, 7, 0)));
+    assert_eq!(state(&config, at(15, 7, 0)), Dnd::Off);
+}
+
+#[test]
+fn schedule_only_starts_on_its_days() {
+    let config = scheduled(vec![Weekday::Wednesday], 22 * 60, 7 * 60);
+
+    assert_eq!(state(&config, at(14, 23, 0)), until(at(15, 7, 0)));
+    // The window which started on Wednesday continues into Thursday.
+    assert_eq!(state(&config, at(15, 6, 0)), until(at(15, 7, 0)));
+    assert_eq!(state(&config, at(15, 23, 0)), Dnd::Off);
+    assert_eq!(state(&config, at(14, 6, 0)), Dnd::Off);
+}
+
+#[test]
+fn until_in_the_future_is_active() {
+    let config = NotificationsConfig {
+        do_not_disturb_until: Some(at(14, 13, 0).timestamp() as u64),
+        ..NotificationsConfig::default()
+    };
+
+    assert_eq!(state(&config, at(14, 12, 0)), until(at(14, 13, 0)));
+}
+
+#[test]
+fn until_in_the_past_is_ignored() {
+    let config = NotificationsConfig {
+        do_not_disturb_until: Some(at(14, 11, 0).timestamp() as u64),
+        ..NotificationsConfig::default()
+    };
+
+    assert_eq!(state(&config, at(14, 12, 0)), Dnd::Off);
+}
+
+#[test]
+fn latest_end_wins() {
+    let mut config = scheduled(Vec::new(), 12 * 60, 13 * 60);
+    config.do_not_disturb_until = Some(at(14, 14, 0).timestamp() as u64);
+    assert_eq!(state(&config, at(14, 12, 30)), until(at(14, 14, 0)));
+
+    config.do_not_disturb_until = Some(at(14, 12, 45).timestamp() as u64);
+    assert_eq!(state(&config, at(14, 12, 30)), until(at(14, 13, 0)));
+}
@@ -0,0 +1,204 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use cosmic_notifications::expiry::{RESUME_GRACE, Timers, timeout};
+use cosmic_notifications_config::NotificationsConfig;
+use cosmic_notifications_util::{Hint, Notification};
+
+fn notification(urgency: u8, expire_timeout: i32) -> Notification {
+    let mut n = Notification::new(
+        "test",
+        1,
+        "",
+        "Summary",
+        "",
+        Vec::new(),
+        HashMap::new(),
+        expire_timeout,
+    );
+    n.hints.push(Hint::Urgency(urgency));
+    n
+}
+
+#[test]
+fn server_default_depends_on_urgency() {
+    let config = NotificationsConfig {
+        default_timeout_low: Some(2000),
+        default_timeout_normal: Some(4000),
+        ..NotificationsConfig::default()
+    };
+
+    assert_eq!(
+        timeout(&config, &notification(0, -1)),
+        Some(Duration::from_millis(2000))
+    );
+    assert_eq!(
+        timeout(&config, &notification(1, -1)),
+        Some(Duration::from_millis(4000))
+    );
+}
+
+#[test]
+fn critical_never_expires_by_default() {
+    let config = NotificationsConfig::default();
+
+    assert_eq!(timeout(&config, &notification(2, -1)), None);
+    assert_eq!(timeout(&config, &notification(2, 0)), None);
+}
+
+#[test]
+fn critical_default_is_configurable() {
+    let config = NotificationsConfig {
+        default_timeout_urgent: Some(20000),
+        ..NotificationsConfig::default()
+    };
+
+    assert_eq!(
+        timeout(&config, &notification(2, -1)),
+        Some(Duration::from_millis(20000))
+    );
+}
+
+#[test]
+fn zero_is_persistent() {
+    let config = NotificationsConfig::default();
+
+    assert_eq!(timeout(&config, &notification(0, 0)), None);
+    assert_eq!(timeout(&config, &notification(1, 0)), None);
+}
+
+#[test]
+fn persistent_expires_when_opted_in() {
+    let config = NotificationsConfig {
+        expire_persistent: true,
+        max_timeout_normal: Some(8000),
+        max_timeout_low: None,
+        default_timeout_low: Some(3000),
+        ..NotificationsConfig::default()
+    };
+
+    assert_eq!(
+        timeout(&config, &notification(1, 0)),
+        Some(Duration::from_millis(8000))
+    );
+    assert_eq!(
+        timeout(&config, &notification(0, 0)),
+        Some(Duration::from_millis(3000))
+    );
+    assert_eq!(timeout(&config, &notification(2, 0)), None);
+}
+
+#[test]
+fn explicit_timeout_is_clamped() {
+    let config = NotificationsConfig {
+        max_timeout_normal: Some(5000),
+        ..NotificationsConfig::default()
+    };
+
+    assert_eq!(
+        timeout(&config, &notification(1, 1000)),
+        Some(Duration::from_millis(1000))
+    );
+    assert_eq!(
+        timeout(&config, &notification(1, 60000)),
+        Some(Duration::from_millis(5000))
+    );
+    assert_eq!(
+        timeout(&config, &notification(2, 60000)),
+        Some(Duration::from_millis(60000))
+    );
+}
+
+#[test]
+fn timer_expires() {
+    let mut timers = Timers::default();
+    let now = Instant::now();
+
+    let timeout = timers.start(1, Some(Duration::from_secs(5)), now).unwrap();
+    assert_eq!(timeout.id, 1);
+    assert_eq!(timeout.after, Duration::from_secs(5));
+    assert!(timers.expired(timeout));
+    assert!(!timers.expired(timeout));
+}
+
+#[test]
+fn persistent_notifications_have_no_timer() {
+    let mut timers = Timers::default();
+
+    assert_eq!(timers.start(1, None, Instant::now()), None);
+}
+
+#[test]
+fn restarted_timer_ignores_stale_timeout() {
+    let mut timers = Timers::default();
+    let now = Instant::now();
+
+    let stale = timers.start(1, Some(Duration::from_secs(5)), now).unwrap();
+    let fresh = timers.start(1, Some(Duration::from_secs(5)), now).unwrap();
+    assert!(!timers.expired(stale));
+    assert!(timers.expired(fresh));
+}
+
+#[test]
+fn paused_timer_resumes_with_remaining_time() {
+    let mut timers = Timers::default();
+    let now = Instant::now();
+
+    let stale = timers.start(1, Some(Duration::from_secs(5)), now).unwrap();
+    timers.pause(1, now + Duration::from_secs(2));
+    assert!(!timers.expired(stale));
+
+    let resumed = timers.resume(1, now + Duration::from_secs(10)).unwrap();
+    assert_eq!(resumed.after, Duration::from_secs(3) + RESUME_GRACE);
+    assert!(timers.expired(resumed));
+}
+
+#[test]
+fn paused_timer_stays_paused_when_restarted() {
+    let mut timers = Timers::default();
+    let now = Instant::now();
+
+    timers.start(1, Some(Duration::from_secs(5)), now);
+    timers.pause(1, now);
+    assert_eq!(timers.start(1, Some(Duration::from_secs(8)), now), None);
+
+    let resumed = timers.resume(1, now).unwrap();
+    assert_eq!(resumed.after, Duration::from_secs(8) + RESUME_GRACE);
+}
+
+#[test]
+fn cancelled_timer_does_not_expire() {
+    let mut timers = Timers::default();
+    let now = Instant::now();
+
+    let timeout = timers.start(1, Some(Duration::from_secs(5)), now).unwrap();
+    timers.cancel(1);
+    assert!(!timers.expired(timeout));
+    assert_eq!(timers.resume(1, now), None);
+}
+
+#[test]
+fn idle_pauses_all_timers() {
+    let mut timers = Timers::default();
+    let now = Instant::now();
+
+    let first = timers.start(1, Some(Duration::from_secs(5)), now).unwrap();
+    timers.start(2, Some(Duration::from_secs(5)), now);
+    assert!(timers.set_idle(true, now, None).is_empty());
+    assert!(!timers.expired(first));
+
+    // Notifications arriving while the user is away are held as well.
+    assert_eq!(timers.start(3, Some(Duration::from_secs(5)), now), None);
+    assert_eq!(timers.resume(1, now), None);
+
+    let mut resumed: Vec<u32> = timers
+        .set_idle(false, now, Some(2))
+        .into_iter()
+        .map(|timeout| timeout.id)
+        .collect();
+    resumed.sort_unstable();
+    assert_eq!(resumed, [1, 3]);
+    assert!(timers.resume(2, now).is_some());
+}
@@ -0,0 +1,115 @@
+#[path = "../cosmic-notifications-util/tests/common/mod.rs"]
+mod common;
+
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, SystemTime},
+};
+
+use common::test_dir;
+use cosmic_notifications::history::{self, History};
+use cosmic_notifications_config::NotificationsConfig;
+use cosmic_notifications_util::{Hint, Image, Notification};
+
+fn notification(id: u32, age: Duration) -> Notification {
+    let mut n = Notification::new(
+        "test",
+        id,
+        "",
+        &format!("Notification {id}"),
+        "",
+        Vec::new(),
+        HashMap::new(),
+        -1,
+    );
+    n.time = SystemTime::now() - age;
+    n
+}
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[test]
+fn retain_drops_oldest_over_count() {
+    let config = NotificationsConfig {
+        history_max_count: 2,
+        history_max_age: None,
+        ..NotificationsConfig::default()
+    };
+    let mut history: VecDeque<_> = (1..=4).rev().map(|id| notification(id, DAY)).collect();
+
+    assert_eq!(history::retain(&mut history, &config), vec![2, 1]);
+    assert_eq!(history.iter().map(|n| n.id).collect::<Vec<_>>(), vec![4, 3]);
+}
+
+#[test]
+fn retain_drops_expired() {
+    let config = NotificationsConfig {
+        history_max_count: 10,
+        history_max_age: Some(2 * DAY.as_secs()),
+        ..NotificationsConfig::default()
+    };
+    let mut history = VecDeque::from([
+        notification(3, Duration::ZERO),
+        notification(2, 3 * DAY),
+        notification(1, DAY),
+    ]);
+
+    assert_eq!(history::retain(&mut history, &config), vec![2]);
+    assert_eq!(history.iter().map(|n| n.id).collect::<Vec<_>>(), vec![3, 1]);
+}
+
+#[tokio::test]
+async fn save_and_load_round_trip() {
+    let dir = test_dir("history-round-trip");
+    let history = History::with_dir(dir.clone());
+
+    let mut with_image = notification(2, Duration::ZERO);
+    with_image.hints.push(Hint::Image(Image::Data {
+        width: 1,
+        height: 1,
+        data: vec![255, 0, 0, 255],
+    }));
+    let mut transient = notification(3, Duration::ZERO);
+    transient.hints.push(Hint::Transient(true));
+    let saved = VecDeque::from([transient, with_image, notification(1, DAY)]);
+
+    history.save(&saved).await.unwrap();
+    let loaded = history.load(&NotificationsConfig::default());
+
+    assert_eq!(loaded.iter().map(|n| n.id).collect::<Vec<_>>(), vec![2, 1]);
+    assert_eq!(loaded[1], saved[2]);
+    let [Hint::Image(Image::File(path))] = loaded[0].hints.as_slice() else {
+        panic!("image was not moved to a file: {:?}", loaded[0].hints);
+    };
+    assert!(path.starts_with(&dir));
+    assert!(path.is_file());
+    _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn save_removes_unused_images() {
+    let dir = test_dir("history-unused-images");
+    let history = History::with_dir(dir.clone());
+
+    let mut with_image = notification(1, Duration::ZERO);
+    with_image.hints.push(Hint::Image(Image::Data {
+        width: 1,
+        height: 1,
+        data: vec![0, 0, 255, 255],
+    }));
+    let saved = VecDeque::from([with_image]);
+    history.save(&saved).await.unwrap();
+    let loaded = history.load(&NotificationsConfig::default());
+    let [Hint::Image(Image::File(path))] = loaded[0].hints.as_slice() else {
+        panic!("image was not moved to a file: {:?}", loaded[0].hints);
+    };
+    assert!(path.is_file());
+
+    // Saving again reuses the file.
+    history.save(&saved).await.unwrap();
+    assert!(path.is_file());
+
+    history.save(&VecDeque::new()).await.unwrap();
+    assert!(!path.exists());
+    _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,610 @@
+mod common;
+
+use std::{collections::HashMap, os::unix::fs::MetadataExt, time::Duration};
+
+use common::Bus;
+use cosmic::iced::futures::{StreamExt, channel::mpsc};
+use cosmic_notifications::subscriptions::notifications::{self, Conns, Event, Input};
+use cosmic_notifications_config::{NotificationsConfig, Rule, RuleAction, RuleMatch};
+use cosmic_notifications_util::{BodyImage, CloseReason, Notification};
+use tokio::net::UnixStream;
+use zbus::{Connection, Guid, connection::Builder, proxy, zvariant::Value};
+
+#[proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    fn get_capabilities(&self) -> zbus::Result<Vec<String>>;
+
+    fn get_server_information(&self) -> zbus::Result<(String, String, String, String)>;
+
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+
+    fn close_notification(&self, id: u32) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn action_invoked(&self, id: u32, action_key: String) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn activation_token(&self, id: u32, activation_token: String) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn notification_closed(&self, id: u32, reason: u32) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn notification_replied(&self, id: u32, text: String) -> zbus::Result<()>;
+}
+
+struct Harness {
+    client: Connection,
+    tx: tokio::sync::mpsc::Sender<Input>,
+    events: mpsc::Receiver<Event>,
+    // Keeps the server connection alive.
+    _server: Connection,
+}
+
+impl Harness {
+    /// Serves the notifications interfaces on one end of a p2p socket and
+    /// connects a client to the other end.
+    async fn new() -> Self {
+        Self::with_restored(&[]).await
+    }
+
+    /// Like [`Harness::new`], with the ids of notifications restored from the
+    /// history.
+    async fn with_restored(restored: &[u32]) -> Self {
+        let (server, client) = UnixStream::pair().unwrap();
+        let guid = Guid::generate();
+        let (server, client) = tokio::join!(
+            Builder::socket(server).p2p().server(guid).unwrap().build(),
+            Builder::socket(client).p2p().build(),
+        );
+        let server = server.unwrap();
+        let client = client.unwrap();
+
+        let conns = Conns::with_connection(server.clone(), restored)
+            .await
+            .unwrap();
+        let tx = conns.tx.clone();
+        let (output, events) = mpsc::channel(100);
+        tokio::spawn(notifications::run(conns, output));
+
+        Self {
+            client,
+            tx,
+            events,
+            _server: server,
+        }
+    }
+
+    async fn proxy(&self) -> NotificationsProxy<'static> {
+        NotificationsProxy::new(&self.client).await.unwrap()
+    }
+
+    async fn notify(&self, proxy: &NotificationsProxy<'_>, replaces_id: u32) -> u32 {
+        notify(proxy, replaces_id).await
+    }
+
+    /// Waits until the inputs sent so far were handled.
+    async fn sync(&mut self) {
+        let marker =
+            Notification::new("sync", u32::MAX, "", "", "", Vec::new(), HashMap::new(), -1);
+        self.tx.send(Input::Notification(marker)).await.unwrap();
+        assert!(matches!(
+            self.events.next().await,
+            Some(Event::Notification(n)) if n.id == u32::MAX
+        ));
+    }
+}
+
+async fn notify(proxy: &NotificationsProxy<'_>, replaces_id: u32) -> u32 {
+    proxy
+        .notify(
+            "test",
+            replaces_id,
+            "",
+            "Summary",
+            "Body",
+            &["default", "Open", "reply", "Reply"],
+            HashMap::from([("transient", Value::from(true))]),
+            -1,
+        )
+        .await
+        .unwrap()
+}
+
+struct BusHarness {
+    bus: Bus,
+    tx: tokio::sync::mpsc::Sender<Input>,
+    events: mpsc::Receiver<Event>,
+    // Keeps the server connection alive.
+    _server: Connection,
+}
+
+impl BusHarness {
+    /// Serves the notifications interfaces on a private bus.
+    async fn new() -> Self {
+        let bus = Bus::start();
+        let server = bus.connect().await;
+        let conns = Conns::with_connection(server.clone(), &[]).await.unwrap();
+        server
+            .request_name("org.freedesktop.Notifications")
+            .await
+            .unwrap();
+        let tx = conns.tx.clone();
+        let (output, events) = mpsc::channel(100);
+        tokio::spawn(notifications::run(conns, output));
+
+        Self {
+            bus,
+            tx,
+            events,
+            _server: server,
+        }
+    }
+
+    /// Connects a new client with its own unique name.
+    async fn client(&self) -> NotificationsProxy<'static> {
+        NotificationsProxy::new(&self.bus.connect().await)
+            .await
+            .unwrap()
+    }
+}
+
+#[tokio::test]
+async fn capabilities() {
+    let harness = Harness::new().await;
+    let proxy = harness.proxy().await;
+
+    let capabilities = proxy.get_capabilities().await.unwrap();
+    for cap in ["actions", "body", "persistence", "sound", "inline-reply"] {
+        assert!(capabilities.iter().any(|c| c == cap), "missing {cap}");
+    }
+    assert!(
+        !(capabilities.iter().any(|c| c == "icon-static")
+            && capabilities.iter().any(|c| c == "icon-multi")),
+        "icon-static and icon-multi are mutually exclusive"
+    );
+}
+
+#[tokio::test]
+async fn server_information() {
+    let harness = Harness::new().await;
+    let proxy = harness.proxy().await;
+
+    let (name, vendor, _version, spec_version) = proxy.get_server_information().await.unwrap();
+    assert_eq!(name, "cosmic-notifications");
+    assert_eq!(vendor, "System76");
+    assert_eq!(spec_version, "1.2");
+}
+
+#[tokio::test]
+async fn notify_forwards_notification() {
+    let mut harness = Harness::new().await;
+    let proxy = harness.proxy().await;
+
+    let first = harness.notify(&proxy, 0).await;
+    let second = harness.notify(&proxy, 0).await;
+    assert_ne!(first, 0);
+    assert_ne!(first, second);
+
+    match harness.events.next().await {
+        Some(Event::Notification(n)) => {
+            assert_eq!(n.id, first);
+            assert_eq!(n.app_name, "test");
+            assert_eq!(n.summary, "Summary");
+            assert_eq!(n.actions.len(), 2);
+            assert!(n.transient());
+        }
+        other => panic!("unexpected event {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn close_notification_emits_closed() {
+    let mut harness = Harness::new().await;
+    let proxy = harness.proxy().await;
+    let mut closed = proxy.receive_notification_closed().await.unwrap();
+
+    let id = harness.notify(&proxy, 0).await;
+    proxy.close_notification(id).await.unwrap();
+
+    loop {
+        match harness.events.next().await {
+            Some(Event::CloseNotification(closed_id)) => {
+                assert_eq!(closed_id, id);
+                break;
+            }
+            Some(_) => {}
+            None => panic!("event stream ended"),
+        }
+    }
+
+    let args = closed.next().await.unwrap();
+    let args = args.args().unwrap();
+    assert_eq!(args.id, id);
+    assert_eq!(args.reason, CloseReason::CloseNotification as u32);
+}
+
+#[tokio::test]
+async fn activation_emits_token_and_action() {
+    let harness = Harness::new().await;
+    let proxy = harness.proxy().await;
+    let mut tokens = proxy.receive_activation_token().await.unwrap();
+    let mut actions = proxy.receive_action_invoked().await.unwrap();
+
+    let id = harness.notify(&proxy, 0).await;
+    harness
+        .tx
+        .send(Input::Activated {
+            token: "token".to_string(),
+            id,
+            action: "reply".to_string(),
+        })
+        .await
+        .unwrap();
+
+    let token = tokens.next().await.unwrap();
+    let token = token.args().unwrap();
+    assert_eq!(token.id, id);
+    assert_eq!(token.activation_token, "token");
+
+    let action = actions.next().await.unwrap();
+    let action = action.args().unwrap();
+    assert_eq!(action.id, id);
+    assert_eq!(action.action_key, "reply");
+}
+
+#[tokio::test]
+async fn reply_emits_replied() {
+    let mut harness = Harness::new().await;
+    let proxy = harness.proxy().await;
+    let mut replied = proxy.receive_notification_replied().await.unwrap();
+
+    let id = proxy
+        .notify(
+            "chat",
+            0,
+            "",
+            "Message",
+            "Hello",
+            &["inline-reply", "Reply"],
+            HashMap::from([(
+                "x-kde-reply-placeholder-text",
+                Value::from("Reply to Alice"),
+            )]),
+            -1,
+        )
+        .await
+        .unwrap();
+    match harness.events.next().await {
+        Some(Event::Notification(n)) => {
+            assert_eq!(n.inline_reply(), Some("Reply"));
+            assert_eq!(n.reply_placeholder(), Some("Reply to Alice"));
+        }
+        other => panic!("unexpected event {other:?}"),
+    }
+
+    harness
+        .tx
+        .send(Input::Replied {
+            id,
+            text: "Hi!".to_string(),
+        })
+        .await
+        .unwrap();
+
+    let args = replied.next().await.unwrap();
+    let args = args.args().unwrap();
+    assert_eq!(args.id, id);
+    assert_eq!(args.text, "Hi!");
+}
+
+#[tokio::test]
+async fn value_hint_is_progress() {
+    let mut harness = Harness::new().await;
+    let proxy = harness.proxy().await;
+
+    for value in [42, 150] {
+        proxy
+            .notify(
+                "files",
+                0,
+                "",
+                "Copying",
+                "",
+                &[],
+                HashMap::from([("value", Value::from(value))]),
+                -1,
+            )
+            .await
+            .unwrap();
+    }
+
+    for expected in [42, 100] {
+        match harness.events.next().await {
+            Some(Event::Notification(n)) => assert_eq!(n.value(), Some(expected)),
+            other => panic!("unexpected event {other:?}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn dismissed_emits_closed() {
+    let harness = Harness::new().await;
+    let proxy = harness.proxy().await;
+    let mut closed = proxy.receive_notification_closed().await.unwrap();
+
+    let id = harness.notify(&proxy, 0).await;
+    harness
+        .tx
+        .send(Input::Closed(id, CloseReason::Dismissed))
+        .await
+        .unwrap();
+
+    let args = closed.next().await.unwrap();
+    let args = args.args().unwrap();
+    assert_eq!(args.id, id);
+    assert_eq!(args.reason, CloseReason::Dismissed as u32);
+}
+
+#[tokio::test]
+async fn inputs_do_not_block_notify() {
+    let harness = Harness::new().await;
+    let proxy = harness.proxy().await;
+
+    // More inputs than the channel holds, while notifications are sent.
+    let tx = harness.tx.clone();
+    let removed = tokio::spawn(async move {
+        for id in 1000..1300 {
+            tx.send(Input::Removed(id)).await.unwrap();
+        }
+    });
+    let notified = async {
+        for _ in 0..10 {
+            harness.notify(&proxy, 0).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(5), notified)
+        .await
+        .expect("Notify was blocked by the inputs");
+    removed.await.unwrap();
+}
+
+#[tokio::test]
+async fn owner_replaces_notification() {
+    let mut harness = BusHarness::new().await;
+    let client = harness.client().await;
+
+    let id = notify(&client, 0).await;
+    assert_eq!(notify(&client, id).await, id);
+
+    assert!(matches!(harness.events.next().await, Some(Event::Notification(n)) if n.id == id));
+    assert!(matches!(harness.events.next().await, Some(Event::Replace(n)) if n.id == id));
+}
+
+#[tokio::test]
+async fn replaces_id_of_other_client_gets_new_id() {
+    let mut harness = BusHarness::new().await;
+    let owner = harness.client().await;
+    let other = harness.client().await;
+
+    let id = notify(&owner, 0).await;
+    let other_id = notify(&other, id).await;
+    assert_ne!(other_id, id);
+
+    assert!(matches!(harness.events.next().await, Some(Event::Notification(n)) if n.id == id));
+    assert!(
+        matches!(harness.events.next().await, Some(Event::Notification(n)) if n.id == other_id)
+    );
+}
+
+#[tokio::test]
+async fn unknown_replaces_id_gets_new_id() {
+    let mut harness = BusHarness::new().await;
+    let client = harness.client().await;
+
+    let id = notify(&client, 1000).await;
+    assert_ne!(id, 1000);
+
+    assert!(matches!(harness.events.next().await, Some(Event::Notification(n)) if n.id == id));
+}
+
+#[tokio::test]
+async fn replacements_are_not_rate_limited() {
+    let mut harness = Harness::new().await;
+    let proxy = harness.proxy().await;
+    let burst = NotificationsConfig::default().rate_limit_burst;
+
+    let id = harness.notify(&proxy, 0).await;
+    for _ in 0..burst * 2 {
+        assert_eq!(harness.notify(&proxy, id).await, id);
+    }
+
+    assert!(matches!(harness.events.next().await, Some(Event::Notification(n)) if n.id == id));
+    for _ in 0..burst * 2 {
+        assert!(matches!(harness.events.next().await, Some(Event::Replace(n)) if n.id == id));
+    }
+}
+
+#[tokio::test]
+async fn body_images_follow_notification() {
+    let mut harness = Harness::new().await;
+    let proxy = harness.proxy().await;
+    let body = r#"Photo <img src="/nonexistent/photo.png" alt="photo">"#;
+
+    let id = proxy
+        .notify("test", 0, "", "Summary", body, &[], HashMap::new(), -1)
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        harness.events.next().await,
+        Some(Event::Notification(n)) if n.id == id && n.body_images.is_empty()
+    ));
+    match harness.events.next().await {
+        Some(Event::BodyImages {
+            id: images_id,
+            body: images_body,
+            images,
+        }) => {
+            assert_eq!(images_id, id);
+            assert_eq!(images_body, body);
+            assert_eq!(
+                images,
+                vec![BodyImage {
+                    image: None,
+                    alt: String::from("photo"),
+                }]
+            );
+        }
+        other => panic!("unexpected event {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn rules_apply_before_forwarding() {
+    let mut harness = Harness::new().await;
+    let proxy = harness.proxy().await;
+    let rule = |summary: &str, action| Rule {
+        matches: RuleMatch {
+            summary: Some(String::from(summary)),
+            ..RuleMatch::default()
+        },
+        actions: vec![action],
+    };
+    let rules = vec![
+        rule("^Dropped$", RuleAction::Drop),
+        rule("^Muted$", RuleAction::Mute),
+        rule("^Urgent$", RuleAction::Urgency(2)),
+    ];
+    harness.tx.send(Input::Rules(rules)).await.unwrap();
+    harness.sync().await;
+
+    let mut ids = Vec::new();
+    for summary in ["Dropped", "Muted", "Urgent"] {
+        let id = proxy
+            .notify("test", 0, "", summary, "", &[], HashMap::new(), -1)
+            .await
+            .unwrap();
+        assert_ne!(id, 0);
+        ids.push(id);
+    }
+
+    assert!(matches!(harness.events.next().await, Some(Event::Hidden(n)) if n.id == ids[1]));
+    assert!(matches!(
+        harness.events.next().await,
+        Some(Event::Notification(n)) if n.id == ids[2] && n.urgency() == 2
+    ));
+}
+
+#[tokio::test]
+async fn ids_continue_after_restored_history() {
+    let harness = Harness::with_restored(&[3, 7, 5]).await;
+    let proxy = harness.proxy().await;
+
+    assert_eq!(harness.notify(&proxy, 0).await, 8);
+    assert_eq!(harness.notify(&proxy, 0).await, 9);
+}
+
+#[tokio::test]
+async fn restored_ids_are_skipped_after_overflow() {
+    let harness = Harness::with_restored(&[u32::MAX, 1, 2]).await;
+    let proxy = harness.proxy().await;
+
+    assert_eq!(harness.notify(&proxy, 0).await, 3);
+}
+
+#[tokio::test]
+async fn only_owner_closes_notification() {
+    let mut harness = BusHarness::new().await;
+    let owner = harness.client().await;
+    let other = harness.client().await;
+
+    let id = notify(&owner, 0).await;
+    assert!(other.close_notification(id).await.is_err());
+    owner.close_notification(id).await.unwrap();
+
+    assert!(matches!(harness.events.next().await, Some(Event::Notification(n)) if n.id == id));
+    // The request of the other client was not forwarded.
+    assert!(matches!(
+        harness.events.next().await,
+        Some(Event::CloseNotification(closed)) if closed == id
+    ));
+}
+
+#[tokio::test]
+async fn signals_are_sent_to_owner_only() {
+    let harness = BusHarness::new().await;
+    let owner = harness.client().await;
+    let other = harness.client().await;
+    let mut owner_closed = owner.receive_notification_closed().await.unwrap();
+    let mut other_closed = other.receive_notification_closed().await.unwrap();
+
+    let id = notify(&owner, 0).await;
+    harness
+        .tx
+        .send(Input::Closed(id, CloseReason::Dismissed))
+        .await
+        .unwrap();
+
+    let args = owner_closed.next().await.unwrap();
+    assert_eq!(args.args().unwrap().id, id);
+    assert!(
+        tokio::time::timeout(Duration::from_millis(200), other_closed.next())
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn signals_are_broadcast_when_enabled() {
+    let harness = BusHarness::new().await;
+    let owner = harness.client().await;
+    let other = harness.client().await;
+    let mut other_closed = other.receive_notification_closed().await.unwrap();
+
+    harness.tx.send(Input::Broadcast(true)).await.unwrap();
+    let id = notify(&owner, 0).await;
+    harness
+        .tx
+        .send(Input::Closed(id, CloseReason::Dismissed))
+        .await
+        .unwrap();
+
+    let args = other_closed.next().await.unwrap();
+    assert_eq!(args.args().unwrap().id, id);
+}
+
+#[tokio::test]
+async fn notification_records_sender_process() {
+    let mut harness = BusHarness::new().await;
+    let client = harness.client().await;
+
+    let id = notify(&client, 0).await;
+
+    match harness.events.next().await {
+        Some(Event::Notification(n)) => {
+            assert_eq!(n.id, id);
+            let provenance = n.provenance.expect("missing provenance");
+            assert_eq!(provenance.pid, Some(std::process::id()));
+            let uid = std::fs::metadata("/proc/self").unwrap().uid();
+            assert_eq!(provenance.uid, Some(uid));
+            assert_eq!(provenance.exe, std::env::current_exe().ok());
+        }
+        other => panic!("unexpected event {other:?}"),
+    }
+}
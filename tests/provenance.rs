@@ -0,0 +1,138 @@
+use std::{fs, os::unix::fs::symlink, path::Path};
+
+use cosmic_notifications::provenance::{PROC, from_proc};
+use cosmic_notifications_util::Provenance;
+
+#[test]
+fn reads_own_process() {
+    let pid = std::process::id();
+    let provenance = from_proc(Path::new(PROC), pid, Some(1000));
+
+    assert_eq!(provenance.pid, Some(pid));
+    assert_eq!(provenance.uid, Some(1000));
+    assert_eq!(provenance.exe, std::env::current_exe().ok());
+    assert_eq!(provenance.flatpak_id, None);
+}
+
+#[test]
+fn reads_flatpak_sandbox() {
+    let proc =
+        std::env::temp_dir().join(format!("cosmic-notifications-proc-{}", std::process::id()));
+    let dir = proc.join("42");
+    fs::create_dir_all(dir.join("root")).unwrap();
+    symlink("/app/bin/firefox", dir.join("exe")).unwrap();
+    fs::write(
+        dir.join("cgroup"),
+        "0::/user.slice/user-1000.slice/user@1000.service/app.slice/app-flatpak-org.mozilla.firefox-1234.scope\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("root/.flatpak-info"),
+        "[Application]\nname=org.mozilla.firefox\nruntime=runtime/org.freedesktop.Platform\n\n[Instance]\nname=other\n",
+    )
+    .unwrap();
+
+    let provenance = from_proc(&proc, 42, None);
+    _ = fs::remove_dir_all(&proc);
+
+    assert_eq!(provenance.pid, Some(42));
+    assert_eq!(
+        provenance.exe.as_deref(),
+        Some(Path::new("/app/bin/firefox"))
+    );
+    assert_eq!(
+        provenance.cgroup.as_deref(),
+        Some(
+            "/user.slice/user-1000.slice/user@1000.service/app.slice/app-flatpak-org.mozilla.firefox-1234.scope"
+        )
+    );
+    assert_eq!(
+        provenance.flatpak_id.as_deref(),
+        Some("org.mozilla.firefox")
+    );
+}
+
+#[test]
+fn reads_apparmor_label() {
+    let proc = std::env::temp_dir().join(format!(
+        "cosmic-notifications-proc-apparmor-{}",
+        std::process::id()
+    ));
+    let dir = proc.join("42");
+    fs::create_dir_all(dir.join("attr")).unwrap();
+    fs::write(dir.join("attr/current"), "snap.firefox.firefox (enforce)\n").unwrap();
+
+    let provenance = from_proc(&proc, 42, None);
+    _ = fs::remove_dir_all(&proc);
+
+    assert_eq!(
+        provenance.apparmor_label.as_deref(),
+        Some("snap.firefox.firefox (enforce)")
+    );
+}
+
+#[test]
+fn missing_process_has_only_ids() {
+    let proc = Path::new("/nonexistent");
+    let provenance = from_proc(proc, 42, Some(1000));
+
+    assert_eq!(provenance.pid, Some(42));
+    assert_eq!(provenance.uid, Some(1000));
+    assert_eq!(provenance.exe, None);
+    assert_eq!(provenance.cgroup, None);
+}
+
+#[test]
+fn sandboxed_app_id() {
+    let provenance = |flatpak_id: Option<&str>, cgroup: &str| Provenance {
+        flatpak_id: flatpak_id.map(str::to_string),
+        cgroup: Some(cgroup.to_string()),
+        ..Provenance::default()
+    };
+    let snap = |cgroup: &str, label: &str| Provenance {
+        apparmor_label: Some(label.to_string()),
+        ..provenance(None, cgroup)
+    };
+
+    assert_eq!(
+        provenance(
+            Some("org.mozilla.firefox"),
+            "/app.slice/app-flatpak-org.mozilla.firefox-1.scope"
+        )
+        .sandboxed_app_id()
+        .as_deref(),
+        Some("org.mozilla.firefox")
+    );
+    assert_eq!(
+        snap(
+            "/user.slice/user-1000.slice/user@1000.service/app.slice/snap.firefox.firefox-2b4d2a14-1d3c-4a8f-9a4e-0c6b1f1e8d2a.scope",
+            "snap.firefox.firefox (enforce)"
+        )
+        .sandboxed_app_id()
+        .as_deref(),
+        Some("firefox_firefox")
+    );
+    assert_eq!(
+        snap(
+            "/system.slice/snap.signal-desktop.signal-desktop.service",
+            "snap.signal-desktop.signal-desktop (enforce)"
+        )
+        .sandboxed_app_id()
+        .as_deref(),
+        Some("signal-desktop_signal-desktop")
+    );
+    // Units named like snaps by unconfined or differently confined processes.
+    let spoofed =
+        "/user.slice/user-1000.slice/user@1000.service/app.slice/snap.firefox.firefox-x.scope";
+    assert_eq!(provenance(None, spoofed).sandboxed_app_id(), None);
+    assert_eq!(snap(spoofed, "unconfined").sandboxed_app_id(), None);
+    assert_eq!(
+        snap(spoofed, "snap.evil.evil (enforce)").sandboxed_app_id(),
+        None
+    );
+    assert_eq!(
+        provenance(None, "/user.slice/user-1000.slice/session-2.scope").sandboxed_app_id(),
+        None
+    );
+    assert_eq!(Provenance::default().sandboxed_app_id(), None);
+}
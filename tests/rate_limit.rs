@@ -0,0 +1,135 @@
+use std::time::{Duration, Instant};
+
+use cosmic_notifications::rate_limit::{RateLimiter, Throttled};
+
+#[test]
+fn burst_is_allowed_then_throttled() {
+    let mut limiter = RateLimiter::new(3, 60);
+    let now = Instant::now();
+
+    for _ in 0..3 {
+        assert_eq!(limiter.check(Some(":1.1"), "a", now), Ok(()));
+    }
+    assert_eq!(
+        limiter.check(Some(":1.1"), "a", now),
+        Err(Throttled {
+            count: 1,
+            summary: None
+        })
+    );
+    assert_eq!(limiter.check(Some(":1.1"), "a", now).unwrap_err().count, 2);
+}
+
+#[test]
+fn tokens_refill_over_time() {
+    let mut limiter = RateLimiter::new(1, 60);
+    let now = Instant::now();
+
+    assert!(limiter.check(None, "a", now).is_ok());
+    assert!(limiter.check(None, "a", now).is_err());
+    assert!(
+        limiter
+            .check(None, "a", now + Duration::from_millis(500))
+            .is_err()
+    );
+    assert!(
+        limiter
+            .check(None, "a", now + Duration::from_secs(2))
+            .is_ok()
+    );
+}
+
+#[test]
+fn zero_burst_disables_limit() {
+    let mut limiter = RateLimiter::new(0, 0);
+    let now = Instant::now();
+
+    for _ in 0..100 {
+        assert!(limiter.check(Some(":1.1"), "a", now).is_ok());
+    }
+}
+
+#[test]
+fn senders_and_apps_are_limited_separately() {
+    let mut limiter = RateLimiter::new(1, 60);
+    let now = Instant::now();
+
+    assert!(limiter.check(Some(":1.1"), "a", now).is_ok());
+    assert!(limiter.check(Some(":1.1"), "b", now).is_ok());
+    assert!(limiter.check(Some(":1.2"), "a", now).is_ok());
+    assert!(limiter.check(Some(":1.1"), "a", now).is_err());
+}
+
+#[test]
+fn changing_app_name_does_not_refill_sender() {
+    let mut limiter = RateLimiter::new(1, 60);
+    let now = Instant::now();
+
+    for app_name in ["a", "b", "c", "d"] {
+        assert!(limiter.check(Some(":1.1"), app_name, now).is_ok());
+    }
+    assert_eq!(
+        limiter.check(Some(":1.1"), "e", now),
+        Err(Throttled {
+            count: 1,
+            summary: None
+        })
+    );
+    limiter.set_summary(Some(":1.1"), "e", 7);
+    assert_eq!(
+        limiter.check(Some(":1.1"), "f", now),
+        Err(Throttled {
+            count: 2,
+            summary: Some(7)
+        })
+    );
+    assert!(limiter.check(Some(":1.2"), "a", now).is_ok());
+}
+
+#[test]
+fn many_senders_are_still_limited() {
+    let mut limiter = RateLimiter::new(1, 60);
+    let now = Instant::now();
+
+    for sender in 0..1000 {
+        let now = now + Duration::from_millis(sender);
+        assert!(
+            limiter
+                .check(Some(&format!(":1.{sender}")), "a", now)
+                .is_ok()
+        );
+    }
+    // The buckets of the most recent senders are kept.
+    let now = now + Duration::from_secs(1);
+    assert!(limiter.check(Some(":1.999"), "a", now).is_err());
+}
+
+#[test]
+fn summary_is_kept_until_allowed_again() {
+    let mut limiter = RateLimiter::new(1, 60);
+    let now = Instant::now();
+
+    assert!(limiter.check(None, "a", now).is_ok());
+    assert!(limiter.check(None, "a", now).is_err());
+    limiter.set_summary(None, "a", 7);
+    assert_eq!(
+        limiter.check(None, "a", now),
+        Err(Throttled {
+            count: 2,
+            summary: Some(7)
+        })
+    );
+
+    assert!(
+        limiter
+            .check(None, "a", now + Duration::from_secs(1))
+            .is_ok()
+    );
+    assert_eq!(
+        limiter.check(None, "a", now + Duration::from_secs(1)),
+        Err(Throttled {
+            count: 1,
+            summary: None
+        })
+    );
+}
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+
+use cosmic_notifications::rules::{Outcome, Rules};
+use cosmic_notifications_config::{Rule, RuleAction, RuleMatch};
+use cosmic_notifications_util::{Hint, Notification};
+
+fn notification(app_name: &str, summary: &str, hints: Vec<Hint>) -> Notification {
+    let mut n = Notification::new(
+        app_name,
+        1,
+        "",
+        summary,
+        "Body",
+        Vec::new(),
+        HashMap::new(),
+        -1,
+    );
+    n.hints.extend(hints);
+    n
+}
+
+fn rule(matches: RuleMatch, actions: Vec<RuleAction>) -> Rule {
+    Rule { matches, actions }
+}
+
+#[test]
+fn all_conditions_must_match() {
+    let rules = Rules::new(&[rule(
+        RuleMatch {
+            app_name: Some(String::from("chat")),
+            category: Some(String::from("im")),
+            ..RuleMatch::default()
+        },
+        vec![RuleAction::Drop],
+    )]);
+
+    let mut n = notification(
+        "chat",
+        "Hi",
+        vec![Hint::Category(String::from("im.received"))],
+    );
+    assert!(rules.apply(&mut n).drop);
+
+    let mut n = notification("chat", "Hi", Vec::new());
+    assert!(!rules.apply(&mut n).drop);
+
+    let mut n = notification(
+        "mail",
+        "Hi",
+        vec![Hint::Category(String::from("im.received"))],
+    );
+    assert!(!rules.apply(&mut n).drop);
+}
+
+#[test]
+fn drop_and_mute() {
+    let rules = Rules::new(&[
+        rule(
+            RuleMatch {
+                app_name: Some(String::from("spam")),
+                ..RuleMatch::default()
+            },
+            vec![RuleAction::Drop],
+        ),
+        rule(
+            RuleMatch {
+                app_name: Some(String::from("quiet")),
+                ..RuleMatch::default()
+            },
+            vec![RuleAction::Mute],
+        ),
+    ]);
+
+    assert_eq!(
+        rules.apply(&mut notification("spam", "", Vec::new())),
+        Outcome {
+            drop: true,
+            mute: false
+        }
+    );
+    assert_eq!(
+        rules.apply(&mut notification("quiet", "", Vec::new())),
+        Outcome {
+            drop: false,
+            mute: true
+        }
+    );
+    assert_eq!(
+        rules.apply(&mut notification("other", "", Vec::new())),
+        Outcome::default()
+    );
+}
+
+#[test]
+fn urgency_and_timeout_are_overridden() {
+    let rules = Rules::new(&[rule(
+        RuleMatch {
+            summary: Some(String::from("^Build (failed|broken)")),
+            ..RuleMatch::default()
+        },
+        vec![RuleAction::Urgency(2), RuleAction::Timeout(0)],
+    )]);
+
+    let mut n = notification("ci", "Build failed", vec![Hint::Urgency(0)]);
+    assert_eq!(rules.apply(&mut n), Outcome::default());
+    assert_eq!(n.urgency(), 2);
+    assert_eq!(
+        n.hints
+            .iter()
+            .filter(|h| matches!(h, Hint::Urgency(_)))
+            .count(),
+        1
+    );
+    assert_eq!(n.expire_timeout, 0);
+
+    let mut n = notification("ci", "Build passed", vec![Hint::Urgency(0)]);
+    rules.apply(&mut n);
+    assert_eq!(n.urgency(), 0);
+    assert_eq!(n.expire_timeout, -1);
+}
+
+#[test]
+fn sound_and_transient_hints_are_set() {
+    let rules = Rules::new(&[rule(
+        RuleMatch::default(),
+        vec![RuleAction::SuppressSound, RuleAction::Transient],
+    )]);
+
+    let mut n = notification("app", "", vec![Hint::Transient(false)]);
+    rules.apply(&mut n);
+    assert!(n.hints.contains(&Hint::SuppressSound(true)));
+    assert!(n.transient());
+}
+
+#[test]
+fn rules_with_invalid_regex_are_skipped() {
+    let rules = Rules::new(&[
+        rule(
+            RuleMatch {
+                summary: Some(String::from("(unclosed")),
+                ..RuleMatch::default()
+            },
+            vec![RuleAction::Drop],
+        ),
+        rule(
+            RuleMatch {
+                body: Some(String::from("Body")),
+                ..RuleMatch::default()
+            },
+            vec![RuleAction::Mute],
+        ),
+    ]);
+
+    let outcome = rules.apply(&mut notification("app", "(unclosed", Vec::new()));
+    assert!(!outcome.drop);
+    assert!(outcome.mute);
+}
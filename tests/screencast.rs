@@ -0,0 +1,149 @@
+mod common;
+
+use std::{collections::HashMap, time::Duration};
+
+use common::Bus;
+use cosmic::iced::futures::{StreamExt, channel::mpsc};
+use cosmic_notifications::subscriptions::screencast::{PORTAL_BACKEND, Sessions, watch};
+use zbus::{
+    interface,
+    zvariant::{ObjectPath, OwnedValue, Value},
+};
+
+#[test]
+fn started_session_is_active_until_closed() {
+    let mut sessions = Sessions::default();
+
+    sessions.start(":1.1", 7, "/session/1");
+    assert!(!sessions.is_active());
+    let session = sessions.take_starting(":1.1", 7).unwrap();
+    sessions.activate(session);
+    assert!(sessions.is_active());
+
+    sessions.close("/session/1");
+    assert!(!sessions.is_active());
+}
+
+#[test]
+fn replies_to_other_calls_are_ignored() {
+    let mut sessions = Sessions::default();
+
+    sessions.start(":1.1", 7, "/session/1");
+    assert_eq!(sessions.take_starting(":1.1", 8), None);
+    assert_eq!(sessions.take_starting(":1.2", 7), None);
+    assert_eq!(
+        sessions.take_starting(":1.1", 7).as_deref(),
+        Some("/session/1")
+    );
+    assert_eq!(sessions.take_starting(":1.1", 7), None);
+}
+
+#[test]
+fn closing_a_starting_session_forgets_it() {
+    let mut sessions = Sessions::default();
+
+    sessions.start(":1.1", 7, "/session/1");
+    sessions.close("/session/1");
+    assert_eq!(sessions.take_starting(":1.1", 7), None);
+}
+
+/// A portal backend which starts every screencast with `response`.
+struct Backend {
+    response: u32,
+}
+
+#[interface(name = "org.freedesktop.impl.portal.ScreenCast")]
+impl Backend {
+    async fn start(
+        &self,
+        _handle: ObjectPath<'_>,
+        _session_handle: ObjectPath<'_>,
+        _app_id: &str,
+        _parent_window: &str,
+        _options: HashMap<&str, Value<'_>>,
+    ) -> (u32, HashMap<String, OwnedValue>) {
+        (self.response, HashMap::new())
+    }
+}
+
+/// Starts a screencast through a backend answering with `response`, and
+/// returns the states sent by the monitor. The backend owns the name of the
+/// portal backend if `named`.
+async fn start_screencast(
+    response: u32,
+    named: bool,
+) -> (Bus, zbus::Connection, mpsc::Receiver<bool>) {
+    let bus = Bus::start();
+    let backend = bus.connect().await;
+    backend
+        .object_server()
+        .at("/org/freedesktop/portal/desktop", Backend { response })
+        .await
+        .unwrap();
+    if named {
+        backend.request_name(PORTAL_BACKEND).await.unwrap();
+    }
+    let portal = bus.connect().await;
+
+    let (tx, mut states) = mpsc::channel(10);
+    tokio::spawn(watch(bus.connect().await, tx));
+    assert_eq!(states.next().await, Some(false));
+
+    let handle = ObjectPath::try_from("/request/1").unwrap();
+    let session = ObjectPath::try_from("/session/1").unwrap();
+    portal
+        .call_method(
+            backend.unique_name().unwrap().as_str(),
+            "/org/freedesktop/portal/desktop",
+            Some("org.freedesktop.impl.portal.ScreenCast"),
+            "Start",
+            &(
+                handle,
+                session,
+                "app",
+                "",
+                HashMap::<&str, Value<'_>>::new(),
+            ),
+        )
+        .await
+        .unwrap();
+    (bus, portal, states)
+}
+
+#[tokio::test]
+async fn monitor_follows_portal_sessions() {
+    let (_bus, portal, mut states) = start_screencast(0, true).await;
+    assert_eq!(states.next().await, Some(true));
+
+    // The call fails as nothing implements it, but is seen by the monitor.
+    _ = portal
+        .call_method(
+            "org.freedesktop.DBus",
+            "/session/1",
+            Some("org.freedesktop.impl.portal.Session"),
+            "Close",
+            &(),
+        )
+        .await;
+    assert_eq!(states.next().await, Some(false));
+}
+
+#[tokio::test]
+async fn cancelled_screencast_is_not_active() {
+    let (_bus, _portal, mut states) = start_screencast(1, true).await;
+    assert!(
+        tokio::time::timeout(Duration::from_millis(200), states.next())
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn replies_of_other_services_are_ignored() {
+    let (_bus, _portal, mut states) = start_screencast(0, false).await;
+    assert!(
+        tokio::time::timeout(Duration::from_millis(200), states.next())
+            .await
+            .is_err()
+    );
+}
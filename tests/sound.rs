@@ -0,0 +1,93 @@
+use std::{fs, path::PathBuf, sync::OnceLock};
+
+use cosmic_notifications::sound::lookup_sound;
+
+/// Creates the sound themes once and points the XDG data directories at them.
+fn data_dir() -> &'static PathBuf {
+    static ROOT: OnceLock<PathBuf> = OnceLock::new();
+    ROOT.get_or_init(|| {
+        let root =
+            std::env::temp_dir().join(format!("cosmic-notifications-sound-{}", std::process::id()));
+        let files: &[(&str, &str)] = &[
+            (
+                "custom/index.theme",
+                "[Sound Theme]\nName=Custom\nInherits=base\nDirectories=stereo\n",
+            ),
+            ("custom/stereo/bell.oga", ""),
+            ("custom/stereo/message-new.wav", ""),
+            (
+                "base/index.theme",
+                "[Sound Theme]\nName=Base\nDirectories=stereo\n",
+            ),
+            ("base/stereo/complete.oga", ""),
+            ("freedesktop/stereo/message.oga", ""),
+            ("freedesktop/stereo/dialog-warning.oga", ""),
+        ];
+        for (file, contents) in files {
+            let path = root.join("sounds").join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+
+        // SAFETY: the variables are only set here, before any lookup reads them.
+        unsafe {
+            std::env::set_var("XDG_DATA_HOME", root.join("home"));
+            std::env::set_var("XDG_DATA_DIRS", &root);
+        }
+        root
+    })
+}
+
+fn sound(file: &str) -> Option<PathBuf> {
+    Some(data_dir().join("sounds").join(file))
+}
+
+#[test]
+fn finds_sound_in_theme() {
+    data_dir();
+    assert_eq!(
+        lookup_sound("custom", "bell"),
+        sound("custom/stereo/bell.oga")
+    );
+    assert_eq!(
+        lookup_sound("base", "complete"),
+        sound("base/stereo/complete.oga")
+    );
+}
+
+#[test]
+fn falls_back_to_shorter_names() {
+    data_dir();
+    assert_eq!(
+        lookup_sound("custom", "message-new-instant"),
+        sound("custom/stereo/message-new.wav")
+    );
+}
+
+#[test]
+fn falls_back_to_inherited_theme() {
+    data_dir();
+    assert_eq!(
+        lookup_sound("custom", "complete"),
+        sound("base/stereo/complete.oga")
+    );
+}
+
+#[test]
+fn falls_back_to_freedesktop_theme() {
+    data_dir();
+    assert_eq!(
+        lookup_sound("custom", "dialog-warning"),
+        sound("freedesktop/stereo/dialog-warning.oga")
+    );
+    assert_eq!(
+        lookup_sound("missing", "dialog-warning"),
+        sound("freedesktop/stereo/dialog-warning.oga")
+    );
+}
+
+#[test]
+fn missing_sound_is_not_found() {
+    data_dir();
+    assert_eq!(lookup_sound("custom", "camera-shutter"), None);
+}